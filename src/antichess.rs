@@ -0,0 +1,433 @@
+/*
+ * antichess.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Board and move generation for antichess (also known as losing chess).
+//!
+//! The chess crate insists on legal chess positions, with kings that can't be
+//! left in check, so it can't represent antichess. This follows the rules used
+//! by lichess and Fairy-Stockfish:
+//!
+//! * Capturing is compulsory, though you may choose between captures.
+//! * The king has no royal status. It can be captured, and there is no check.
+//! * Pawns may also promote to a king.
+//! * There is no castling.
+//! * You win by running out of moves, whether by losing all your pieces or being stalemated.
+
+use crate::error::{Error, Result};
+use chess::{
+    get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_pawn_quiets,
+    get_rook_moves, BitBoard, ChessMove, Color, File, Piece, Rank, Square, ALL_PIECES, EMPTY,
+    NUM_COLORS, NUM_PIECES,
+};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1";
+
+/// Pieces a pawn may promote to, which unlike chess includes the king.
+const PROMOTION_PIECES: [Piece; 5] = [
+    Piece::Queen,
+    Piece::Rook,
+    Piece::Bishop,
+    Piece::Knight,
+    Piece::King,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AntichessBoard {
+    pieces: [BitBoard; NUM_PIECES],
+    colors: [BitBoard; NUM_COLORS],
+    side_to_move: Color,
+
+    /// The square a pawn may capture onto en passant, as in a FEN.
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+}
+
+impl AntichessBoard {
+    /// Parses a FEN. Castling rights are ignored, since antichess has no castling.
+    pub fn from_fen(fen: &str) -> Result<Self> {
+        let invalid = || Error::InvalidFen(fen.into());
+        let fields = fen.split_whitespace().collect::<Vec<_>>();
+        if fields.len() < 4 {
+            return Err(invalid());
+        }
+
+        let mut board = AntichessBoard {
+            pieces: [EMPTY; NUM_PIECES],
+            colors: [EMPTY; NUM_COLORS],
+            side_to_move: Color::White,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        };
+
+        // Pieces, from the eighth rank down
+        let ranks = fields[0].split('/').collect::<Vec<_>>();
+        if ranks.len() != 8 {
+            return Err(invalid());
+        }
+
+        for (row, text) in ranks.iter().enumerate() {
+            let rank = Rank::from_index(7 - row);
+            let mut file = 0;
+
+            for letter in text.chars() {
+                if let Some(skip) = letter.to_digit(10) {
+                    file += skip as usize;
+                    continue;
+                }
+
+                if file >= 8 {
+                    return Err(invalid());
+                }
+
+                let piece = match letter.to_ascii_lowercase() {
+                    'p' => Piece::Pawn,
+                    'n' => Piece::Knight,
+                    'b' => Piece::Bishop,
+                    'r' => Piece::Rook,
+                    'q' => Piece::Queen,
+                    'k' => Piece::King,
+                    _ => return Err(invalid()),
+                };
+
+                let color = if letter.is_ascii_uppercase() {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+
+                board.put(
+                    Square::make_square(rank, File::from_index(file)),
+                    piece,
+                    color,
+                );
+                file += 1;
+            }
+
+            if file != 8 {
+                return Err(invalid());
+            }
+        }
+
+        board.side_to_move = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(invalid()),
+        };
+
+        board.en_passant = match fields[3] {
+            "-" => None,
+            square => Some(Square::from_str(square).map_err(|_| invalid())?),
+        };
+
+        if let Some(value) = fields.get(4) {
+            board.halfmove_clock = value.parse().map_err(|_| invalid())?;
+        }
+
+        if let Some(value) = fields.get(5) {
+            board.fullmove_number = value.parse().map_err(|_| invalid())?;
+        }
+
+        Ok(board)
+    }
+
+    #[inline]
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    #[inline]
+    pub fn color_combined(&self, color: Color) -> BitBoard {
+        self.colors[color.to_index()]
+    }
+
+    #[inline]
+    pub fn combined(&self) -> BitBoard {
+        self.colors[0] | self.colors[1]
+    }
+
+    pub fn piece_on(&self, square: Square) -> Option<Piece> {
+        let square = BitBoard::from_square(square);
+
+        ALL_PIECES
+            .iter()
+            .copied()
+            .find(|piece| self.pieces[piece.to_index()] & square != EMPTY)
+    }
+
+    pub fn color_on(&self, square: Square) -> Option<Color> {
+        let square = BitBoard::from_square(square);
+
+        [Color::White, Color::Black]
+            .iter()
+            .copied()
+            .find(|color| self.colors[color.to_index()] & square != EMPTY)
+    }
+
+    /// Lists every legal move. If any capture is possible, only captures are legal.
+    pub fn legal_moves(&self) -> Vec<ChessMove> {
+        let color = self.side_to_move;
+        let ours = self.color_combined(color);
+        let theirs = self.color_combined(!color);
+        let occupied = self.combined();
+        let en_passant = self.en_passant.map_or(EMPTY, BitBoard::from_square);
+
+        let mut captures = Vec::new();
+        let mut quiets = Vec::new();
+
+        for source in ours {
+            let piece = self.piece_on(source).expect("No piece on occupied square");
+            let targets = match piece {
+                Piece::Pawn => {
+                    get_pawn_attacks(source, color, theirs | en_passant)
+                        | get_pawn_quiets(source, color, occupied)
+                }
+                Piece::Knight => get_knight_moves(source),
+                Piece::Bishop => get_bishop_moves(source, occupied),
+                Piece::Rook => get_rook_moves(source, occupied),
+                Piece::Queen => {
+                    get_bishop_moves(source, occupied) | get_rook_moves(source, occupied)
+                }
+                Piece::King => get_king_moves(source),
+            } & !ours;
+
+            for dest in targets {
+                let moves = if self.is_capture(ChessMove::new(source, dest, None)) {
+                    &mut captures
+                } else {
+                    &mut quiets
+                };
+
+                if piece == Piece::Pawn && dest.get_rank() == color.to_their_backrank() {
+                    for &promotion in &PROMOTION_PIECES {
+                        moves.push(ChessMove::new(source, dest, Some(promotion)));
+                    }
+                } else {
+                    moves.push(ChessMove::new(source, dest, None));
+                }
+            }
+        }
+
+        if captures.is_empty() {
+            quiets
+        } else {
+            captures
+        }
+    }
+
+    #[inline]
+    pub fn legal(&self, chess_move: ChessMove) -> bool {
+        self.legal_moves().contains(&chess_move)
+    }
+
+    /// Whether this move takes a piece, including en passant.
+    pub fn is_capture(&self, chess_move: ChessMove) -> bool {
+        let dest = chess_move.get_dest();
+
+        self.color_on(dest) == Some(!self.side_to_move)
+            || (self.piece_on(chess_move.get_source()) == Some(Piece::Pawn)
+                && Some(dest) == self.en_passant)
+    }
+
+    /// Makes a move, which is assumed to be legal.
+    pub fn make_move_new(&self, chess_move: ChessMove) -> Self {
+        let mut board = *self;
+        let color = self.side_to_move;
+        let (source, dest) = (chess_move.get_source(), chess_move.get_dest());
+        let piece = self.piece_on(source).expect("No piece to move");
+        let is_capture = self.is_capture(chess_move);
+
+        if let Some(captured) = self.piece_on(dest) {
+            board.take(dest, captured, !color);
+        } else if piece == Piece::Pawn && Some(dest) == self.en_passant {
+            let captured = dest.ubackward(color);
+            board.take(captured, Piece::Pawn, !color);
+        }
+
+        board.take(source, piece, color);
+        board.put(dest, chess_move.get_promotion().unwrap_or(piece), color);
+
+        // A pawn moving two squares can be captured en passant on the square it skipped
+        board.en_passant = None;
+        if piece == Piece::Pawn
+            && (source.get_rank().to_index() as i32 - dest.get_rank().to_index() as i32).abs() == 2
+        {
+            board.en_passant = Some(source.uforward(color));
+        }
+
+        board.halfmove_clock = if is_capture || piece == Piece::Pawn {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+
+        if color == Color::Black {
+            board.fullmove_number += 1;
+        }
+
+        board.side_to_move = !color;
+        board
+    }
+
+    /// The player who has won, if the game is over.
+    ///
+    /// A player with no moves left, whether from having no pieces or being stalemated, wins.
+    pub fn winner(&self) -> Option<Color> {
+        if self.legal_moves().is_empty() {
+            Some(self.side_to_move)
+        } else {
+            None
+        }
+    }
+
+    /// How many pieces this player has left.
+    #[inline]
+    pub fn material(&self, color: Color) -> u32 {
+        self.color_combined(color).popcnt()
+    }
+
+    // Helpers
+
+    fn put(&mut self, square: Square, piece: Piece, color: Color) {
+        let square = BitBoard::from_square(square);
+        self.pieces[piece.to_index()] |= square;
+        self.colors[color.to_index()] |= square;
+    }
+
+    fn take(&mut self, square: Square, piece: Piece, color: Color) {
+        let square = !BitBoard::from_square(square);
+        self.pieces[piece.to_index()] &= square;
+        self.colors[color.to_index()] &= square;
+    }
+}
+
+impl Default for AntichessBoard {
+    fn default() -> Self {
+        AntichessBoard::from_fen(STARTING_FEN).expect("Invalid starting FEN")
+    }
+}
+
+impl Display for AntichessBoard {
+    /// Writes the board as a FEN.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in 0..8 {
+            let rank = Rank::from_index(7 - row);
+            let mut empty = 0;
+
+            for file in 0..8 {
+                let square = Square::make_square(rank, File::from_index(file));
+
+                match (self.piece_on(square), self.color_on(square)) {
+                    (Some(piece), Some(color)) => {
+                        if empty > 0 {
+                            write!(f, "{}", empty)?;
+                            empty = 0;
+                        }
+
+                        write!(f, "{}", piece.to_string(color))?;
+                    }
+                    _ => empty += 1,
+                }
+            }
+
+            if empty > 0 {
+                write!(f, "{}", empty)?;
+            }
+
+            if row < 7 {
+                write!(f, "/")?;
+            }
+        }
+
+        let side = match self.side_to_move {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+
+        write!(f, " {} - ", side)?;
+        match self.en_passant {
+            Some(square) => write!(f, "{}", square)?,
+            None => write!(f, "-")?,
+        }
+
+        write!(f, " {} {}", self.halfmove_clock, self.fullmove_number)
+    }
+}
+
+/// Parses a move in UCI notation, which may promote to a king.
+pub fn parse_move(text: &str) -> Result<ChessMove> {
+    match text.strip_suffix('k') {
+        Some(text) if text.len() == 4 => {
+            let chess_move =
+                ChessMove::from_str(text).map_err(|_| Error::IllegalMove(text.into()))?;
+
+            Ok(ChessMove::new(
+                chess_move.get_source(),
+                chess_move.get_dest(),
+                Some(Piece::King),
+            ))
+        }
+        _ => ChessMove::from_str(text).map_err(|_| Error::IllegalMove(text.into())),
+    }
+}
+
+#[test]
+fn forced_captures() {
+    let board = AntichessBoard::default();
+    assert_eq!(board.legal_moves().len(), 20);
+    assert_eq!(board.to_string(), STARTING_FEN);
+
+    // After 1. e3 b5, white must take on b5
+    let board = board
+        .make_move_new(parse_move("e2e3").expect("Invalid move"))
+        .make_move_new(parse_move("b7b5").expect("Invalid move"));
+    let moves = board
+        .legal_moves()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(moves, vec!["f1b5"]);
+
+    // Kings can be captured like anything else
+    let board = AntichessBoard::from_fen("8/8/8/8/8/8/1k6/K7 w - - 0 1").expect("Invalid FEN");
+    let capture = parse_move("a1b2").expect("Invalid move");
+    assert_eq!(board.legal_moves(), vec![capture]);
+
+    // Which leaves black with nothing, so black wins
+    let board = board.make_move_new(capture);
+    assert_eq!(board.material(Color::Black), 0);
+    assert_eq!(board.winner(), Some(Color::Black));
+}
+
+#[test]
+fn promotions_and_en_passant() {
+    let board = AntichessBoard::from_fen("8/P7/8/8/8/8/8/7k w - - 0 1").expect("Invalid FEN");
+    assert_eq!(board.legal_moves().len(), 5);
+
+    let promotion = parse_move("a7a8k").expect("Invalid move");
+    assert!(board.legal(promotion));
+    assert_eq!(
+        board.make_move_new(promotion).piece_on(Square::A8),
+        Some(Piece::King),
+    );
+
+    let board = AntichessBoard::from_fen("8/8/8/3Pp3/8/8/8/7k w - e6 0 1").expect("Invalid FEN");
+    let capture = parse_move("d5e6").expect("Invalid move");
+    assert_eq!(board.legal_moves(), vec![capture]);
+
+    let board = board.make_move_new(capture);
+    assert_eq!(board.piece_on(Square::E5), None);
+    assert_eq!(board.material(Color::Black), 1);
+}
@@ -0,0 +1,59 @@
+/*
+ * backend/control.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Flags for controlling a search while it runs.
+//!
+//! These are set by the input thread as soon as the GUI sends `go`,
+//! `stop` or `ponderhit`, and checked by every backend search.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchControl {
+    stopped: Arc<AtomicBool>,
+    pondering: Arc<AtomicBool>,
+}
+
+impl SearchControl {
+    #[inline]
+    pub fn new() -> Self {
+        SearchControl::default()
+    }
+
+    /// Resets the flags for a new search.
+    pub fn start(&self, pondering: bool) {
+        self.stopped.store(false, Ordering::SeqCst);
+        self.pondering.store(pondering, Ordering::SeqCst);
+    }
+
+    /// Asks the current search to finish as soon as possible.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.pondering.store(false, Ordering::SeqCst);
+    }
+
+    /// The opponent played the move we were pondering on, so this is now a regular search.
+    pub fn ponderhit(&self) {
+        self.pondering.store(false, Ordering::SeqCst);
+    }
+
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.load(Ordering::SeqCst)
+    }
+}
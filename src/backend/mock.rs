@@ -0,0 +1,252 @@
+/*
+ * backend/mock.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! A fake UCI engine which runs in-process, for testing without Stockfish.
+//!
+//! It replies to searches of scripted positions with one `info` line per
+//! scripted move (up to the `MultiPV` setting), then picks the first as its
+//! best move. Any other position is scored as 0 centipawns, with the first
+//! legal move as the best move. Searches with a `movetime` take that long,
+//! unless they are stopped first.
+//!
+//! It can also be scripted to fail a number of searches, to test how we recover.
+//! The count is shared between every copy of the script, so a restarted
+//! engine carries on from where the previous one failed.
+
+use crate::chess960;
+use chess::{Board, ChessMove, MoveGen};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use vampirc_uci::{parse_one, UciMessage, UciTimeControl};
+
+/// How the fake engine fails a search.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// Exits without replying, as if it crashed.
+    Crash,
+
+    /// Never replies again, as if it hung.
+    Hang,
+
+    /// Replies with an unreadable score, then its best move.
+    Malformed,
+}
+
+/// Scripted search results, keyed by the FEN of the position searched.
+#[derive(Debug, Clone, Default)]
+pub struct MockScript {
+    positions: HashMap<String, Vec<(String, i32)>>,
+    failure: Option<Failure>,
+    failures_left: Arc<AtomicUsize>,
+    remembering: bool,
+}
+
+impl MockScript {
+    #[inline]
+    pub fn new() -> Self {
+        MockScript::default()
+    }
+
+    /// Scripts the lines to report when searching this position, best first.
+    ///
+    /// Each line is a move in UCI notation and its score in centipawns,
+    /// from the perspective of the side to move.
+    pub fn position(mut self, fen: &str, lines: &[(&str, i32)]) -> Self {
+        let board = Board::from_str(fen).expect("Invalid FEN in mock script");
+        let lines = lines
+            .iter()
+            .map(|&(chess_move, score)| (chess_move.into(), score))
+            .collect();
+
+        self.positions.insert(board.to_string(), lines);
+        self
+    }
+
+    /// Adds the number of searches since the last `ucinewgame` to every score,
+    /// like a real engine whose results depend on what's left in its hash table.
+    pub fn remembering(mut self) -> Self {
+        self.remembering = true;
+        self
+    }
+
+    /// Fails the next `times` searches, across every engine started from this script.
+    pub fn fail(mut self, failure: Failure, times: usize) -> Self {
+        self.failure = Some(failure);
+        self.failures_left = Arc::new(AtomicUsize::new(times));
+        self
+    }
+
+    /// Uses up one of the scripted failures, if there are any left.
+    fn next_failure(&self) -> Option<Failure> {
+        let failure = self.failure?;
+        self.failures_left
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                left.checked_sub(1)
+            })
+            .ok()
+            .map(|_| failure)
+    }
+
+    /// Starts the fake engine, returning the input to write commands to
+    /// and the receiver which its output lines are sent to.
+    pub fn start(&self) -> (MockInput, Receiver<io::Result<String>>) {
+        let (command_sender, commands) = mpsc::channel::<String>();
+        let (output, receiver) = mpsc::channel();
+        let script = self.clone();
+
+        thread::spawn(move || {
+            let mut board = Board::default();
+            let mut multipv = 1;
+            let mut hung = false;
+            let mut searches = 0;
+
+            let send = |line: String| output.send(Ok(line)).is_ok();
+
+            while let Ok(command) = commands.recv() {
+                if hung {
+                    continue;
+                }
+
+                let replies = match parse_one(&command) {
+                    UciMessage::Uci => vec!["id name Mock".into(), "uciok".into()],
+                    UciMessage::IsReady => vec!["readyok".into()],
+                    UciMessage::UciNewGame => {
+                        searches = 0;
+                        continue;
+                    }
+                    UciMessage::SetOption { name, value } if name == "MultiPV" => {
+                        multipv = value.and_then(|value| value.parse().ok()).unwrap_or(1);
+                        continue;
+                    }
+                    UciMessage::Position { fen: Some(fen), .. } => {
+                        // Positions are sent as Shredder-FENs when playing Chess960
+                        let (fen_board, _) =
+                            chess960::parse_fen(fen.as_str()).expect("Invalid FEN sent to mock");
+                        board = fen_board;
+                        continue;
+                    }
+                    UciMessage::Go { time_control, .. } => match script.next_failure() {
+                        Some(Failure::Crash) => break,
+                        Some(Failure::Hang) => {
+                            hung = true;
+                            continue;
+                        }
+                        Some(Failure::Malformed) => vec![
+                            "info depth 1 score cp lots".into(),
+                            format!("bestmove {}", first_move(&board)),
+                        ],
+                        None => {
+                            // Like a real engine, use all of the time we're given, unless stopped
+                            if let Some(UciTimeControl::MoveTime(move_time)) = time_control {
+                                let deadline =
+                                    Instant::now() + move_time.to_std().unwrap_or_default();
+
+                                while let Some(remaining) =
+                                    deadline.checked_duration_since(Instant::now())
+                                {
+                                    match commands.recv_timeout(remaining) {
+                                        Ok(command) if command == "stop" => break,
+                                        Ok(_) | Err(RecvTimeoutError::Timeout) => (),
+                                        Err(RecvTimeoutError::Disconnected) => return,
+                                    }
+                                }
+                            }
+
+                            let drift = if script.remembering { searches } else { 0 };
+                            searches += 1;
+
+                            script.search(&board, multipv, drift)
+                        }
+                    },
+                    UciMessage::Quit => break,
+                    _ => continue,
+                };
+
+                if !replies.into_iter().all(send) {
+                    break;
+                }
+            }
+        });
+
+        let input = MockInput {
+            buffer: Vec::new(),
+            commands: command_sender,
+        };
+
+        (input, receiver)
+    }
+
+    fn search(&self, board: &Board, multipv: usize, drift: i32) -> Vec<String> {
+        let lines = match self.positions.get(&board.to_string()) {
+            Some(lines) => lines.clone(),
+            None => vec![(first_move(board).to_string(), 0)],
+        };
+
+        let mut replies = lines
+            .iter()
+            .take(multipv)
+            .enumerate()
+            .map(|(index, (chess_move, score))| {
+                format!(
+                    "info depth 1 multipv {} score cp {} pv {}",
+                    index + 1,
+                    score + drift,
+                    chess_move,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        replies.push(format!("bestmove {}", lines[0].0));
+        replies
+    }
+}
+
+fn first_move(board: &Board) -> ChessMove {
+    MoveGen::new_legal(board)
+        .next()
+        .expect("Mock asked to search a finished game")
+}
+
+/// Commands written to the fake engine, which are passed to it line by line.
+#[derive(Debug)]
+pub struct MockInput {
+    buffer: Vec<u8>,
+    commands: Sender<String>,
+}
+
+impl Write for MockInput {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+
+        while let Some(index) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line = self.buffer.drain(..=index).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+
+            if self.commands.send(line).is_err() {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+        }
+
+        Ok(bytes.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,777 @@
+/*
+ * backend/mod.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Module for communicating with the backend UCI engine.
+//!
+//! By default this is Stockfish, a very solid chess engine which we are
+//! using for our various "modes" of chess engine operation, but any engine
+//! which speaks UCI (such as Lc0 or Ethereal) can be used instead.
+//!
+//! This application is essentially "piping through" what
+//! the backend determines, with modifications depending on the mode.
+//!
+//! The backend process is supervised: if it crashes or stops responding,
+//! it is restarted and the failed evaluation is retried.
+//!
+//! Searches of independent positions can be spread across a pool of
+//! several backend processes, to make use of more CPU cores.
+//!
+//! The processes aren't started until something asks the backend for an
+//! evaluation, so engines which never do can run without a backend installed.
+
+mod control;
+#[cfg(test)]
+pub mod mock;
+mod pool;
+mod process;
+mod supervisor;
+
+use self::pool::BackendPool;
+use self::supervisor::Supervisor;
+
+pub use self::control::SearchControl;
+use crate::antichess::AntichessBoard;
+use crate::cache::{CacheKey, EvaluationCache};
+use crate::chess960::{self, Castling};
+use crate::clock::{split_budget, LOW_TIME_NODES};
+use crate::config::Configuration;
+use crate::error::Result;
+use crate::game::Variant;
+use crate::score::{Score, ScoredMove};
+use chess::{Board, BoardStatus, ChessMove, MoveGen};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use vampirc_uci::{UciMessage, UciSearchControl, UciTimeControl};
+
+/// How to score every legal move in a position.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EvaluationMode {
+    /// Runs a single search with `MultiPV` set to the number of legal moves,
+    /// collecting the score of each principal variation.
+    MultiPv,
+
+    /// Runs a separate search on the position resulting from each legal move.
+    PerMove,
+}
+
+/// How to launch the backend engine process.
+#[derive(Debug, Clone)]
+pub struct BackendCommand {
+    /// Path to the engine binary, or its name if it's in `$PATH`.
+    pub program: OsString,
+
+    /// Command-line arguments to pass to the engine.
+    pub args: Vec<OsString>,
+
+    /// Directory to run the engine in, if not the current one.
+    pub working_dir: Option<PathBuf>,
+
+    /// Runs a fake engine in-process instead.
+    #[cfg(test)]
+    pub mock: Option<mock::MockScript>,
+}
+
+impl Default for BackendCommand {
+    fn default() -> Self {
+        BackendCommand {
+            program: OsString::from("stockfish"),
+            args: Vec::new(),
+            working_dir: None,
+
+            #[cfg(test)]
+            mock: None,
+        }
+    }
+}
+
+/// A UCI option to set on the backend engine after it starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendOption {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for BackendOption {
+    type Err = &'static str;
+
+    /// Parses an option in the form `Name=Value`.
+    ///
+    /// Option names may contain spaces, such as `Skill Level=5`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or("Option must be in the form Name=Value")?;
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err("Option name cannot be empty");
+        }
+
+        Ok(BackendOption {
+            name: name.into(),
+            value: value.trim().into(),
+        })
+    }
+}
+
+/// Settings for supervising the backend process.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Supervision {
+    /// How long to wait for a line from the backend before assuming it's hung.
+    ///
+    /// This is in addition to any time budget for the current search.
+    pub timeout: Option<Duration>,
+
+    /// How many times to restart the backend and retry a failed evaluation.
+    pub max_retries: u32,
+}
+
+/// Limits on a single backend search.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// How many nodes the backend may search, if limited.
+    pub nodes: Option<u64>,
+
+    /// How long the backend may search, if limited.
+    pub move_time: Option<Duration>,
+
+    /// How many plies deep the backend may search, if limited.
+    pub depth: Option<u8>,
+
+    /// Castling rights to send along with each position, when playing Chess960.
+    pub chess960: Option<Castling>,
+}
+
+impl SearchLimits {
+    fn go_message(&self) -> UciMessage {
+        let time_control = self.move_time.map(|move_time| {
+            let move_time =
+                vampirc_uci::Duration::from_std(move_time).expect("Time budget out of range");
+            UciTimeControl::MoveTime(move_time)
+        });
+
+        UciMessage::Go {
+            time_control,
+            search_control: Some(UciSearchControl {
+                search_moves: Vec::new(),
+                mate: None,
+                depth: self.depth,
+                nodes: self.nodes,
+            }),
+        }
+    }
+
+    /// Divides the time limit between the given number of consecutive searches.
+    ///
+    /// If there isn't enough time to give each search its own limit,
+    /// they're limited to a handful of nodes instead, so the searches
+    /// as a whole still finish within the budget.
+    fn split(self, searches: usize) -> Self {
+        match self.move_time {
+            Some(move_time) => match split_budget(move_time, searches) {
+                Some(move_time) => SearchLimits {
+                    move_time: Some(move_time),
+                    ..self
+                },
+                None => SearchLimits {
+                    move_time: None,
+                    nodes: Some(
+                        self.nodes
+                            .map_or(LOW_TIME_NODES, |nodes| nodes.min(LOW_TIME_NODES)),
+                    ),
+                    ..self
+                },
+            },
+            None => self,
+        }
+    }
+}
+
+/// The backend processes, started the first time they're needed.
+#[derive(Debug)]
+struct Processes {
+    command: BackendCommand,
+    options: Vec<BackendOption>,
+    supervision: Supervision,
+    count: usize,
+    log_file: Arc<File>,
+    running: Option<RunningProcesses>,
+}
+
+#[derive(Debug)]
+struct RunningProcesses {
+    supervisor: Supervisor,
+    pool: Option<BackendPool>,
+}
+
+impl Processes {
+    /// Returns the running processes, starting them if this is the first use.
+    fn get(&mut self) -> Result<&mut RunningProcesses> {
+        if self.running.is_none() {
+            log!(self.log_file, "Starting backend engine");
+
+            let supervisor = Supervisor::spawn(
+                &self.command,
+                &self.options,
+                self.supervision,
+                Arc::clone(&self.log_file),
+            )?;
+
+            // The main process counts towards the total
+            let pool = match self.count {
+                0 | 1 => None,
+                processes => Some(BackendPool::spawn(
+                    processes - 1,
+                    &self.command,
+                    &self.options,
+                    self.supervision,
+                    Arc::clone(&self.log_file),
+                )?),
+            };
+
+            self.running = Some(RunningProcesses { supervisor, pool });
+        }
+
+        Ok(self
+            .running
+            .as_mut()
+            .expect("Backend processes not started"))
+    }
+}
+
+#[derive(Debug)]
+pub struct Backend {
+    processes: Processes,
+    control: SearchControl,
+    cache: EvaluationCache,
+    nodes_to_search: Option<u64>,
+    depth_limit: Option<u8>,
+    time_budget: Option<Duration>,
+    chess960: Option<Castling>,
+    evaluation_mode: EvaluationMode,
+    log_file: Arc<File>,
+}
+
+impl Backend {
+    // Constructor
+    pub fn new(config: &Configuration, cache: EvaluationCache, control: SearchControl) -> Self {
+        let log_file = Arc::clone(&config.log_file);
+        let processes = Processes {
+            command: config.backend.clone(),
+            options: config.backend_options.clone(),
+            supervision: config.supervision,
+            count: config.backend_processes,
+            log_file: Arc::clone(&log_file),
+            running: None,
+        };
+
+        Backend {
+            processes,
+            control,
+            cache,
+            nodes_to_search: config.backend_nodes,
+            depth_limit: None,
+            time_budget: None,
+            chess960: None,
+            evaluation_mode: config.evaluation_mode,
+            log_file,
+        }
+    }
+
+    // Helpers
+
+    fn limits(&self) -> SearchLimits {
+        SearchLimits {
+            nodes: self.nodes_to_search,
+            depth: self.depth_limit,
+            move_time: self.time_budget,
+            chess960: self.chess960,
+        }
+    }
+
+    fn cache_insert(&mut self, key: CacheKey, scored_moves: Vec<ScoredMove>) {
+        // A stopped search didn't run to its limits, so its result doesn't match the key
+        if self.control.is_stopped() {
+            return;
+        }
+
+        if let Err(error) = self.cache.insert(key, scored_moves) {
+            log!(
+                self.log_file,
+                "Unable to write to evaluation cache: {}",
+                error
+            );
+        }
+    }
+
+    fn cache_key(board: &Board, lines: usize, limits: SearchLimits) -> CacheKey {
+        CacheKey {
+            hash: board.get_hash(),
+            nodes: limits.nodes,
+            depth: limits.depth,
+            move_time: limits.move_time,
+            lines,
+            chess960: limits.chess960,
+        }
+    }
+
+    // Methods
+
+    /// Starts the backend processes now, rather than on the first evaluation.
+    ///
+    /// This way, a missing or broken backend is reported straight away.
+    pub fn start(&mut self) -> Result<()> {
+        self.processes.get().map(|_| ())
+    }
+
+    pub fn set_nodes_to_search(&mut self, nodes_to_search: Option<u64>) {
+        self.nodes_to_search = nodes_to_search;
+    }
+
+    pub fn set_depth_limit(&mut self, depth_limit: Option<u8>) {
+        self.depth_limit = depth_limit;
+    }
+
+    pub fn set_evaluation_mode(&mut self, evaluation_mode: EvaluationMode) {
+        self.evaluation_mode = evaluation_mode;
+    }
+
+    /// Sets the castling rights of the current game when playing Chess960, or `None` otherwise.
+    pub fn set_chess960(&mut self, castling: Option<Castling>) {
+        self.chess960 = castling;
+    }
+
+    /// Sets how long we may spend deciding on the next move.
+    ///
+    /// When multiple searches are needed to make a decision,
+    /// this budget is divided between them.
+    pub fn set_time_budget(&mut self, budget: Option<Duration>) {
+        log!(self.log_file, "Setting search time budget: {:?}", budget);
+        self.time_budget = budget;
+    }
+
+    /// Evaluates a board.
+    ///
+    /// It determines the score of a board by having the backend return
+    /// its preferred move, as well as its value change based on it.
+    pub fn evaluate_position(&mut self, board: &Board) -> Result<ScoredMove> {
+        let (scored_move, _) = self.evaluate_position_with_ponder(board)?;
+        Ok(scored_move)
+    }
+
+    /// Evaluates a board, also returning the reply the backend expects to its best move.
+    ///
+    /// The reply is only available when the position was actually searched,
+    /// rather than found in the cache.
+    pub fn evaluate_position_with_ponder(
+        &mut self,
+        board: &Board,
+    ) -> Result<(ScoredMove, Option<ChessMove>)> {
+        log!(
+            self.log_file,
+            "Asking backend to evaluate position (hash {})",
+            board.get_hash(),
+        );
+
+        let limits = self.limits();
+        let key = Self::cache_key(board, 1, limits);
+        if let Some(&[scored_move]) = self.cache.get(&key).as_deref() {
+            log!(self.log_file, "Found cached evaluation: {:?}", scored_move);
+            return Ok((scored_move, None));
+        }
+
+        let control = &self.control;
+        let (scored_move, ponder_move) = self
+            .processes
+            .get()?
+            .supervisor
+            .run(|process| process.search_position(board, limits, control))?;
+
+        self.cache_insert(key, vec![scored_move]);
+        Ok((scored_move, ponder_move))
+    }
+
+    /// Asks the backend for its move in an antichess position.
+    ///
+    /// Scores in variants aren't comparable with regular chess, so these are never cached.
+    pub fn evaluate_antichess_position(&mut self, board: &AntichessBoard) -> Result<ChessMove> {
+        log!(
+            self.log_file,
+            "Asking backend to choose an antichess move ({})",
+            board,
+        );
+
+        let fen = board.to_string();
+        let limits = self.limits();
+        let control = &self.control;
+        self.processes
+            .get()?
+            .supervisor
+            .run(|process| process.search_variant(Variant::Antichess, &fen, limits, control))
+    }
+
+    /// Evaluates all possible moves from the board position.
+    ///
+    /// Depending on the configured `EvaluationMode`, this either uses a
+    /// single `MultiPV` search or one search per legal move. Either way,
+    /// it returns all the moves and their calculated scores in a list.
+    pub fn evaluate_possible_moves_unsorted(&mut self, board: &Board) -> Result<Vec<ScoredMove>> {
+        log!(
+            self.log_file,
+            "Asking backend to evaluate all possible moves for board (hash {}, mode {:?})",
+            board.get_hash(),
+            self.evaluation_mode,
+        );
+
+        log!(
+            self.log_file,
+            "Evaluation cache has {} entries ({} hits, {} misses)",
+            self.cache.len(),
+            self.cache.hits(),
+            self.cache.misses(),
+        );
+
+        match self.evaluation_mode {
+            EvaluationMode::MultiPv => self.evaluate_possible_moves_multipv(board),
+            EvaluationMode::PerMove => self.evaluate_possible_moves_per_move(board),
+        }
+    }
+
+    /// Lists the legal moves, including castling moves only possible in Chess960.
+    fn legal_moves(&self, board: &Board) -> Vec<ChessMove> {
+        let mut moves = MoveGen::new_legal(board).collect::<Vec<_>>();
+        if let Some(castling) = self.chess960 {
+            moves.extend(castling.moves(board));
+        }
+
+        moves
+    }
+
+    /// Evaluates all possible moves using a single `MultiPV` search.
+    ///
+    /// Each principal variation starts with a different legal move, and its
+    /// score is already from our perspective, so no negation is needed.
+    /// Any move which the backend didn't report a line for (for instance, if it
+    /// caps the number of lines) is evaluated separately using `evaluate_moves()`.
+    fn evaluate_possible_moves_multipv(&mut self, board: &Board) -> Result<Vec<ScoredMove>> {
+        let moves = self.legal_moves(board);
+        let limits = self.limits();
+
+        let key = Self::cache_key(board, moves.len(), limits);
+        let started = Instant::now();
+        let line_scores = match self.cache.get(&key) {
+            Some(cached) => {
+                log!(self.log_file, "Found cached MultiPV evaluation");
+
+                cached
+                    .iter()
+                    .map(|scored_move| (scored_move.chess_move, scored_move.score))
+                    .collect()
+            }
+            None => {
+                let control = &self.control;
+                let line_scores =
+                    self.processes.get()?.supervisor.run(|process| {
+                        process.search_multipv(board, moves.len(), limits, control)
+                    })?;
+
+                let cached = line_scores
+                    .iter()
+                    .map(|(&chess_move, &score)| ScoredMove { chess_move, score })
+                    .collect();
+
+                self.cache_insert(key, cached);
+                line_scores
+            }
+        };
+
+        log!(
+            self.log_file,
+            "Backend reported {} of {} lines",
+            line_scores.len(),
+            moves.len(),
+        );
+
+        // Any moves without a line need their own search, which has to come
+        // out of whatever is left of the time budget after the MultiPV search.
+        // Finished games are given the same immediate scores as in
+        // evaluate_moves(), since engines score stalemates as 0cp.
+        let remaining = SearchLimits {
+            move_time: limits
+                .move_time
+                .map(|move_time| move_time.saturating_sub(started.elapsed())),
+            ..limits
+        };
+
+        let missing = moves
+            .iter()
+            .copied()
+            .filter(|chess_move| {
+                chess960::make_move(board, *chess_move).status() != BoardStatus::Ongoing
+                    || !line_scores.contains_key(chess_move)
+            })
+            .collect::<Vec<_>>();
+
+        let mut missing_scores = missing
+            .iter()
+            .copied()
+            .zip(self.evaluate_moves(board, &missing, remaining)?)
+            .collect::<HashMap<_, _>>();
+
+        let scored_moves = moves
+            .into_iter()
+            .map(|chess_move| {
+                let score = match missing_scores.remove(&chess_move) {
+                    Some(score) => score,
+                    None => line_scores[&chess_move],
+                };
+
+                ScoredMove { chess_move, score }
+            })
+            .collect();
+
+        Ok(scored_moves)
+    }
+
+    /// Evaluates all possible moves by running a search for each one.
+    ///
+    /// See `evaluate_moves()`.
+    fn evaluate_possible_moves_per_move(&mut self, board: &Board) -> Result<Vec<ScoredMove>> {
+        let moves = self.legal_moves(board);
+        let limits = self.limits();
+        let scores = self.evaluate_moves(board, &moves, limits)?;
+
+        let scored_moves = moves
+            .into_iter()
+            .zip(scores)
+            .map(|(chess_move, score)| ScoredMove { chess_move, score })
+            .collect();
+
+        Ok(scored_moves)
+    }
+
+    /// Determines the score of making each of the given moves on this board.
+    ///
+    /// Every resulting position gets its own search, spread across the process
+    /// pool if there is one. The time budget is divided evenly between the
+    /// searches each process has to run. Each search is isolated, so the
+    /// scores are the same whichever process ran them.
+    fn evaluate_moves(
+        &mut self,
+        board: &Board,
+        moves: &[ChessMove],
+        limits: SearchLimits,
+    ) -> Result<Vec<Score>> {
+        let processes = self.processes.count.max(1);
+        let rounds = (moves.len() + processes - 1) / processes;
+        let limits = limits.split(rounds);
+
+        let mut scores = vec![None; moves.len()];
+        let mut pending = Vec::new();
+
+        for (index, &chess_move) in moves.iter().enumerate() {
+            let possible_board = chess960::make_move(board, chess_move);
+
+            match possible_board.status() {
+                BoardStatus::Ongoing => {
+                    let key = Self::cache_key(&possible_board, 1, limits);
+                    match self.cache.get(&key).as_deref() {
+                        Some(&[scored_move]) => scores[index] = Some(-scored_move.score),
+                        _ => pending.push((index, possible_board, key)),
+                    }
+                }
+
+                // Game is finished, return immediate score
+                BoardStatus::Checkmate => scores[index] = Some(Score::OurMate(0)),
+                BoardStatus::Stalemate => scores[index] = Some(Score::Stalemate(0)),
+            }
+        }
+
+        log!(
+            self.log_file,
+            "Searching {} of {} moves using {} backend processes",
+            pending.len(),
+            moves.len(),
+            processes,
+        );
+
+        let boards = pending
+            .iter()
+            .map(|&(_, possible_board, _)| possible_board)
+            .collect::<Vec<_>>();
+
+        let results = self.search_boards(boards, limits)?;
+
+        for ((index, _, key), scored_move) in pending.into_iter().zip(results) {
+            // We are attempting to score / recommend speculative moves,
+            // but the search checks moves beyond that, that is, for the
+            // opposite player. So we only keep the score from it, and pair
+            // it with the possible move from our caller.
+            //
+            // Similarly, we negate the score from this position because
+            // it was calculated from the opponent's perspective.
+            scores[index] = Some(-scored_move.score);
+            self.cache_insert(key, vec![scored_move]);
+        }
+
+        Ok(scores
+            .into_iter()
+            .map(|score| score.expect("Move was not evaluated"))
+            .collect())
+    }
+
+    /// Runs a search on each board, spread across the process pool if there is one.
+    ///
+    /// Every search is isolated, with or without a pool, so the scores
+    /// don't depend on how many processes ran them or in which order.
+    fn search_boards(
+        &mut self,
+        boards: Vec<Board>,
+        limits: SearchLimits,
+    ) -> Result<Vec<ScoredMove>> {
+        // Everything was cached or finished, so there's no need to start the backend
+        if boards.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let running = self.processes.get()?;
+        let control = &self.control;
+        match running.pool {
+            Some(ref mut pool) => pool
+                .search_all(&mut running.supervisor, boards, limits, control)
+                .into_iter()
+                .collect(),
+            None => {
+                let supervisor = &mut running.supervisor;
+                boards
+                    .iter()
+                    .map(|possible_board| {
+                        supervisor.run(|process| {
+                            process.search_position_isolated(possible_board, limits, control)
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Evaluates all possible moves from the board position, sorted by increasing score value.
+    ///
+    /// See `evaluate_possible_moves_unsorted()`.
+    pub fn evaluate_possible_moves(&mut self, board: &Board) -> Result<Vec<ScoredMove>> {
+        let mut scored_moves = self.evaluate_possible_moves_unsorted(board)?;
+        scored_moves.sort_by_key(|scored_move| scored_move.score);
+        Ok(scored_moves)
+    }
+}
+
+#[test]
+fn parse_options() {
+    let option: BackendOption = "Skill Level = 5".parse().expect("Unable to parse option");
+    assert_eq!(option.name, "Skill Level");
+    assert_eq!(option.value, "5");
+
+    let option: BackendOption = "SyzygyPath=/a=b".parse().expect("Unable to parse option");
+    assert_eq!(option.name, "SyzygyPath");
+    assert_eq!(option.value, "/a=b");
+
+    assert!("Hash".parse::<BackendOption>().is_err());
+    assert!("=256".parse::<BackendOption>().is_err());
+}
+
+#[test]
+fn pool_matches_sequential() {
+    use self::mock::MockScript;
+    use crate::engine::EngineKind;
+
+    let board = Board::from_str("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").expect("Invalid FEN");
+    // Scores drift with each search the mock remembers, as a real engine's hash table would make them
+    let mut script = MockScript::new().remembering();
+    for (index, chess_move) in MoveGen::new_legal(&board).enumerate() {
+        let possible_board = board.make_move_new(chess_move);
+        let score = index as i32 * 10 - 25;
+        script = script.position(&possible_board.to_string(), &[("e8d8", score)]);
+    }
+
+    let evaluate = |processes| {
+        let mut config = Configuration::mock(EngineKind::Random, script.clone());
+        config.evaluation_mode = EvaluationMode::PerMove;
+        config.backend_processes = processes;
+
+        let mut backend = Backend::new(&config, EvaluationCache::new(0), SearchControl::new());
+        backend
+            .evaluate_possible_moves(&board)
+            .expect("Unable to evaluate moves")
+    };
+
+    let sequential = evaluate(1);
+    assert_eq!(sequential.len(), 6);
+    assert_eq!(sequential[0].score, Score::Centipawns(-25));
+    assert_eq!(evaluate(4), sequential);
+}
+
+#[test]
+fn starts_lazily() {
+    use crate::engine::EngineKind;
+    use crate::error::Error;
+
+    let mut config = Configuration::mock(EngineKind::Random, mock::MockScript::new());
+    config.backend = BackendCommand {
+        program: OsString::from("/nonexistent/mallard-chess-backend"),
+        ..BackendCommand::default()
+    };
+
+    // Nothing is spawned until an evaluation is requested
+    let mut backend = Backend::new(&config, EvaluationCache::new(0), SearchControl::new());
+    match backend.evaluate_position(&Board::default()) {
+        Err(Error::BackendSpawn(_)) => (),
+        result => panic!("Backend started unexpectedly: {:?}", result),
+    }
+}
+
+#[test]
+fn low_clock() {
+    use crate::engine::EngineKind;
+
+    // Twenty searches would need twice the budget if each got the minimum
+    let budget = Duration::from_millis(100);
+    let board = Board::default();
+
+    for &mode in &[EvaluationMode::PerMove, EvaluationMode::MultiPv] {
+        let mut config = Configuration::mock(EngineKind::Random, mock::MockScript::new());
+        config.evaluation_mode = mode;
+
+        let mut backend = Backend::new(&config, EvaluationCache::new(0), SearchControl::new());
+        backend.start().expect("Unable to start backend");
+        backend.set_time_budget(Some(budget));
+
+        let started = Instant::now();
+        let scored_moves = backend
+            .evaluate_possible_moves(&board)
+            .expect("Unable to evaluate moves");
+        let elapsed = started.elapsed();
+
+        assert_eq!(scored_moves.len(), 20);
+        assert!(
+            elapsed < budget + Duration::from_millis(50),
+            "{:?} search took {:?}",
+            mode,
+            elapsed,
+        );
+    }
+}
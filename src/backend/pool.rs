@@ -0,0 +1,165 @@
+/*
+ * backend/pool.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Extra backend processes for running independent searches in parallel.
+//!
+//! Each helper process is owned by its own worker thread. For a batch of
+//! searches, every worker (and the caller, using the main backend process)
+//! takes searches from a shared queue until it's empty. Results are tagged
+//! with their index in the batch, so the order they finish in doesn't matter.
+
+use super::supervisor::Supervisor;
+use super::{BackendCommand, BackendOption, SearchControl, SearchLimits, Supervision};
+use crate::error::{Error, Result};
+use crate::score::ScoredMove;
+use chess::Board;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Queue = Arc<Mutex<VecDeque<(usize, Board)>>>;
+type SearchResult = (usize, Result<ScoredMove>);
+
+/// A batch of searches for a worker to help with.
+struct Task {
+    queue: Queue,
+    limits: SearchLimits,
+    control: SearchControl,
+    results: Sender<SearchResult>,
+}
+
+#[derive(Debug)]
+struct Worker {
+    tasks: Option<Sender<Task>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug)]
+pub struct BackendPool {
+    workers: Vec<Worker>,
+    log_file: Arc<File>,
+}
+
+impl BackendPool {
+    /// Starts `helpers` additional backend processes.
+    pub fn spawn(
+        helpers: usize,
+        command: &BackendCommand,
+        options: &[BackendOption],
+        supervision: Supervision,
+        log_file: Arc<File>,
+    ) -> Result<Self> {
+        log!(log_file, "Starting {} helper backend processes", helpers);
+
+        let mut workers = Vec::with_capacity(helpers);
+        for _ in 0..helpers {
+            let supervisor =
+                Supervisor::spawn(command, options, supervision, Arc::clone(&log_file))?;
+            let (sender, receiver) = mpsc::channel();
+            let thread = thread::spawn(move || run_worker(supervisor, receiver));
+
+            workers.push(Worker {
+                tasks: Some(sender),
+                thread: Some(thread),
+            });
+        }
+
+        Ok(BackendPool { workers, log_file })
+    }
+
+    /// Runs an isolated search on each board, returning the results in the same order.
+    ///
+    /// The calling thread takes part using `main`, the main backend process.
+    pub fn search_all(
+        &mut self,
+        main: &mut Supervisor,
+        boards: Vec<Board>,
+        limits: SearchLimits,
+        control: &SearchControl,
+    ) -> Vec<Result<ScoredMove>> {
+        let count = boards.len();
+        let queue: Queue = Arc::new(Mutex::new(boards.into_iter().enumerate().collect()));
+        let (sender, receiver) = mpsc::channel();
+
+        for worker in &self.workers {
+            if let Some(ref tasks) = worker.tasks {
+                // If the worker is gone, the remaining processes pick up its share
+                let _ = tasks.send(Task {
+                    queue: Arc::clone(&queue),
+                    limits,
+                    control: control.clone(),
+                    results: sender.clone(),
+                });
+            }
+        }
+
+        while let Some((index, board)) = pop(&queue) {
+            let result =
+                main.run(|process| process.search_position_isolated(&board, limits, control));
+            let _ = sender.send((index, result));
+        }
+
+        // Once every worker has dropped its sender, all results are in
+        drop(sender);
+
+        let mut results = (0..count).map(|_| None).collect::<Vec<_>>();
+        for (index, result) in receiver {
+            results[index] = Some(result);
+        }
+
+        results
+            .into_iter()
+            .map(|result| {
+                result.unwrap_or_else(|| {
+                    log!(self.log_file, "Helper backend stopped mid-search");
+                    Err(Error::BackendExited)
+                })
+            })
+            .collect()
+    }
+}
+
+impl Drop for BackendPool {
+    fn drop(&mut self) {
+        // Closing the task channels lets the workers finish and shut down their processes
+        for worker in &mut self.workers {
+            worker.tasks = None;
+        }
+
+        for worker in &mut self.workers {
+            if let Some(thread) = worker.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+fn pop(queue: &Queue) -> Option<(usize, Board)> {
+    queue.lock().expect("Search queue poisoned").pop_front()
+}
+
+fn run_worker(mut supervisor: Supervisor, tasks: Receiver<Task>) {
+    for task in tasks {
+        while let Some((index, board)) = pop(&task.queue) {
+            let result = supervisor.run(|process| {
+                process.search_position_isolated(&board, task.limits, &task.control)
+            });
+
+            if task.results.send((index, result)).is_err() {
+                break;
+            }
+        }
+    }
+}
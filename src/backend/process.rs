@@ -0,0 +1,558 @@
+/*
+ * backend/process.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! A single running backend engine process.
+//!
+//! Output from the process is read on a separate thread, so that
+//! we can give up waiting if the engine hangs instead of blocking forever.
+
+use super::{BackendCommand, BackendOption, SearchControl, SearchLimits};
+use crate::antichess;
+use crate::chess960::{self, Castling};
+use crate::error::{Error, Result};
+use crate::game::Variant;
+use crate::score::{Score, ScoredMove};
+use chess::{Board, ChessMove};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use vampirc_uci::{parse_one, UciFen, UciInfoAttribute, UciMessage};
+
+/// How often to check whether a running search should be stopped.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Where commands to the backend are written.
+#[derive(Debug)]
+enum Output {
+    Pipe(ChildStdin),
+
+    #[cfg(test)]
+    Mock(super::mock::MockInput),
+}
+
+impl Write for Output {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Pipe(stdin) => stdin.write(bytes),
+
+            #[cfg(test)]
+            Output::Mock(input) => input.write(bytes),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Pipe(stdin) => stdin.flush(),
+
+            #[cfg(test)]
+            Output::Mock(input) => input.flush(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BackendProcess {
+    /// The engine's process, or `None` if it's running in-process.
+    process: Option<Child>,
+    input: Receiver<io::Result<String>>,
+    output: Output,
+    timeout: Option<Duration>,
+
+    /// Whether the backend has been told to play Chess960.
+    chess960: bool,
+
+    /// Which variant the backend has been told to play.
+    variant: Variant,
+    log_file: Arc<File>,
+}
+
+impl BackendProcess {
+    // Constructor
+    pub fn spawn(
+        command: &BackendCommand,
+        options: &[BackendOption],
+        timeout: Option<Duration>,
+        log_file: Arc<File>,
+    ) -> Result<Self> {
+        log!(log_file, "Starting backend engine: {:?}", command);
+
+        #[cfg(test)]
+        if let Some(ref script) = command.mock {
+            let (output, input) = script.start();
+            let mut backend = BackendProcess {
+                process: None,
+                input,
+                output: Output::Mock(output),
+                timeout,
+                chess960: false,
+                variant: Variant::Standard,
+                log_file,
+            };
+
+            backend.handshake(options)?;
+            return Ok(backend);
+        }
+
+        let mut process = Command::new(&command.program);
+        process
+            .args(&command.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+
+        if let Some(ref working_dir) = command.working_dir {
+            process.current_dir(working_dir);
+        }
+
+        let mut process = process.spawn().map_err(Error::BackendSpawn)?;
+
+        let stdin = process.stdin.take().expect("No stdin opened");
+        let stdout = process.stdout.take().expect("No stdout opened");
+
+        // Forward each line from the engine, stopping once the pipe closes.
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut backend = BackendProcess {
+            process: Some(process),
+            input: receiver,
+            output: Output::Pipe(stdin),
+            timeout,
+            chess960: false,
+            variant: Variant::Standard,
+            log_file,
+        };
+
+        backend.handshake(options)?;
+        Ok(backend)
+    }
+
+    /// Performs the UCI startup sequence, configuring any options.
+    ///
+    /// Options can only be set after `uciok`, and we wait for `readyok`
+    /// to be sure the engine has applied them before searching.
+    fn handshake(&mut self, options: &[BackendOption]) -> Result<()> {
+        self.send(UciMessage::Uci)?;
+
+        loop {
+            match self.receive(None)? {
+                UciMessage::UciOk => break,
+                UciMessage::Id {
+                    name: Some(name), ..
+                } => log!(self.log_file, "Backend engine name: {}", name),
+                _ => (),
+            }
+        }
+
+        for option in options {
+            log!(
+                self.log_file,
+                "Setting backend option {} = {}",
+                option.name,
+                option.value,
+            );
+
+            self.send(UciMessage::SetOption {
+                name: option.name.clone(),
+                value: Some(option.value.clone()),
+            })?;
+        }
+
+        self.wait_until_ready()
+    }
+
+    fn wait_until_ready(&mut self) -> Result<()> {
+        self.send(UciMessage::IsReady)?;
+
+        while self.receive(None)? != UciMessage::ReadyOk {}
+        Ok(())
+    }
+
+    // Communication
+
+    /// Waits for the next message from the engine.
+    ///
+    /// The engine may take up to `extra_time` longer than the configured
+    /// timeout to respond, which is used to allow for the search itself.
+    fn receive(&mut self, extra_time: Option<Duration>) -> Result<UciMessage> {
+        let line = match self.timeout {
+            None => self.input.recv().map_err(|_| Error::BackendExited)?,
+            Some(timeout) => {
+                let timeout = timeout + extra_time.unwrap_or_default();
+
+                match self.input.recv_timeout(timeout) {
+                    Ok(line) => line,
+                    Err(RecvTimeoutError::Timeout) => return Err(Error::BackendTimeout),
+                    Err(RecvTimeoutError::Disconnected) => return Err(Error::BackendExited),
+                }
+            }
+        }?;
+
+        Ok(parse_one(&line))
+    }
+
+    /// Waits for the next message from the engine while it searches.
+    ///
+    /// Once the search is stopped, the engine is told to finish up. Searches
+    /// without any limits only end when stopped, so until then they can't hang.
+    fn receive_searching(
+        &mut self,
+        limits: SearchLimits,
+        control: &SearchControl,
+        stop_sent: &mut bool,
+    ) -> Result<UciMessage> {
+        let line = self.receive_searching_line(limits, control, stop_sent)?;
+        Ok(parse_one(&line))
+    }
+
+    /// Like `receive_searching()`, but returns the line as it was sent.
+    fn receive_searching_line(
+        &mut self,
+        limits: SearchLimits,
+        control: &SearchControl,
+        stop_sent: &mut bool,
+    ) -> Result<String> {
+        let mut waiting_since = Instant::now();
+
+        loop {
+            if !*stop_sent && control.is_stopped() {
+                log!(self.log_file, "Stopping backend search");
+                self.send(UciMessage::Stop)?;
+                *stop_sent = true;
+                waiting_since = Instant::now();
+            }
+
+            match self.input.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => return Ok(line?),
+                Err(RecvTimeoutError::Disconnected) => return Err(Error::BackendExited),
+                Err(RecvTimeoutError::Timeout) => (),
+            }
+
+            let unlimited = limits.move_time.is_none()
+                && limits.nodes.is_none()
+                && limits.depth.is_none()
+                && !*stop_sent;
+            if let (Some(timeout), false) = (self.timeout, unlimited) {
+                if waiting_since.elapsed() > timeout + limits.move_time.unwrap_or_default() {
+                    return Err(Error::BackendTimeout);
+                }
+            }
+        }
+    }
+
+    pub fn send<D: Display>(&mut self, command: D) -> Result<()> {
+        writeln!(self.output, "{}", command)?;
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn set_position(&mut self, board: &Board, chess960: Option<Castling>) -> Result<()> {
+        self.set_variant(Variant::Standard)?;
+
+        // The backend needs to know how castling rights are written before seeing the position
+        if chess960.is_some() != self.chess960 {
+            self.chess960 = chess960.is_some();
+            self.send(UciMessage::SetOption {
+                name: "UCI_Chess960".into(),
+                value: Some(self.chess960.to_string()),
+            })?;
+        }
+
+        let fen = match chess960 {
+            Some(castling) => castling.fen(board),
+            None => board.to_string(),
+        };
+
+        self.send(UciMessage::Position {
+            startpos: false,
+            fen: Some(UciFen(fen)),
+            moves: Vec::new(),
+        })
+    }
+
+    /// Switches the backend to another variant, which only variant-aware engines understand.
+    ///
+    /// Nothing is sent until a variant is first used, so regular backends never see the option.
+    fn set_variant(&mut self, variant: Variant) -> Result<()> {
+        if variant != self.variant {
+            self.variant = variant;
+            self.send(UciMessage::SetOption {
+                name: "UCI_Variant".into(),
+                value: Some(variant.uci_name().into()),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn set_multipv(&mut self, lines: usize) -> Result<()> {
+        self.send(UciMessage::SetOption {
+            name: "MultiPV".into(),
+            value: Some(lines.to_string()),
+        })
+    }
+
+    // Searches
+
+    /// Searches a position, returning the backend's best move and its score,
+    /// along with the reply it expects, if any.
+    pub fn search_position(
+        &mut self,
+        board: &Board,
+        limits: SearchLimits,
+        control: &SearchControl,
+    ) -> Result<(ScoredMove, Option<ChessMove>)> {
+        self.set_position(board, limits.chess960)?;
+        self.send(limits.go_message())?;
+
+        let chess_move;
+        let ponder_move;
+        let mut score = None;
+        let mut stop_sent = false;
+
+        loop {
+            match self.receive_searching(limits, control, &mut stop_sent)? {
+                // Finished evaluating
+                UciMessage::BestMove { best_move, ponder } => {
+                    log!(
+                        self.log_file,
+                        "Backend finished, found best move: {:?} (ponder {:?})",
+                        best_move,
+                        ponder,
+                    );
+
+                    chess_move = read_move(board, best_move, limits);
+                    ponder_move = match ponder {
+                        Some(ponder) if board.legal(chess_move) => {
+                            Some(read_move(&board.make_move_new(chess_move), ponder, limits))
+                        }
+                        _ => ponder,
+                    };
+                    break;
+                }
+
+                // Record scores as we receive them
+                // The last score before BestMove is the evaluation
+                UciMessage::Info(attributes) => {
+                    for attribute in &attributes {
+                        log!(self.log_file, "Backend sent information: {:?}", attribute);
+
+                        match attribute {
+                            // Providing a material difference in centipawns
+                            UciInfoAttribute::Score {
+                                cp: Some(centipawns),
+                                ..
+                            } => score = Some(Score::Centipawns(*centipawns)),
+
+                            // Found a mate in X moves
+                            UciInfoAttribute::Score {
+                                mate: Some(moves), ..
+                            } => score = Some(Score::from_mate(*moves)),
+
+                            // Ignore other info lines
+                            _ => (),
+                        }
+                    }
+                }
+
+                // Ignore unknown or unexpected messages
+                _ => (),
+            }
+        }
+
+        // If no score value has been set, then the backend isn't behaving properly.
+        let score = score.ok_or(Error::BackendMissingScore)?;
+
+        // Return result
+        //
+        // This is the best move it found, and the score of this move,
+        // which rates our current position.
+        Ok((ScoredMove { chess_move, score }, ponder_move))
+    }
+
+    /// Searches a position from a cleared state.
+    ///
+    /// Sending `ucinewgame` first empties the backend's hash table, so the result
+    /// only depends on the position and limits, not on what was searched before.
+    /// This is what lets a pool of processes agree with a single one on their
+    /// evaluations, at the cost of an extra round trip for each search.
+    pub fn search_position_isolated(
+        &mut self,
+        board: &Board,
+        limits: SearchLimits,
+        control: &SearchControl,
+    ) -> Result<ScoredMove> {
+        self.send(UciMessage::UciNewGame)?;
+        self.wait_until_ready()?;
+
+        let (scored_move, _) = self.search_position(board, limits, control)?;
+        Ok(scored_move)
+    }
+
+    /// Searches a position in a chess variant, returning the backend's best move.
+    ///
+    /// This requires a backend which supports `UCI_Variant`, such as Fairy-Stockfish.
+    pub fn search_variant(
+        &mut self,
+        variant: Variant,
+        fen: &str,
+        limits: SearchLimits,
+        control: &SearchControl,
+    ) -> Result<ChessMove> {
+        self.set_variant(variant)?;
+        self.send(UciMessage::Position {
+            startpos: false,
+            fen: Some(UciFen(fen.into())),
+            moves: Vec::new(),
+        })?;
+        self.send(limits.go_message())?;
+
+        let mut stop_sent = false;
+
+        loop {
+            // The UCI parser doesn't know that pawns can promote to kings, so read the move ourselves
+            let line = self.receive_searching_line(limits, control, &mut stop_sent)?;
+            let best_move = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["bestmove", text, ..] => antichess::parse_move(text)?,
+
+                // Ignore info lines and anything else
+                _ => continue,
+            };
+
+            log!(
+                self.log_file,
+                "Backend finished {} search, found best move: {}",
+                variant.uci_name(),
+                best_move,
+            );
+
+            return Ok(best_move);
+        }
+    }
+
+    /// Runs a `MultiPV` search, returning the score for the first move of each line.
+    pub fn search_multipv(
+        &mut self,
+        board: &Board,
+        lines: usize,
+        limits: SearchLimits,
+        control: &SearchControl,
+    ) -> Result<HashMap<ChessMove, Score>> {
+        let mut line_scores = HashMap::new();
+        let mut stop_sent = false;
+
+        self.set_multipv(lines)?;
+        self.set_position(board, limits.chess960)?;
+        self.send(limits.go_message())?;
+
+        loop {
+            match self.receive_searching(limits, control, &mut stop_sent)? {
+                // Finished evaluating
+                UciMessage::BestMove { best_move, .. } => {
+                    log!(
+                        self.log_file,
+                        "Backend finished MultiPV search, found best move: {:?}",
+                        best_move,
+                    );
+                    break;
+                }
+
+                // Record the score of each line's first move.
+                // Later (deeper) lines overwrite earlier ones.
+                UciMessage::Info(attributes) => {
+                    let mut first_move = None;
+                    let mut score = None;
+
+                    for attribute in &attributes {
+                        match attribute {
+                            UciInfoAttribute::Pv(pv) => {
+                                first_move = pv
+                                    .first()
+                                    .map(|&chess_move| read_move(board, chess_move, limits));
+                            }
+                            UciInfoAttribute::Score {
+                                cp: Some(centipawns),
+                                ..
+                            } => score = Some(Score::Centipawns(*centipawns)),
+                            UciInfoAttribute::Score {
+                                mate: Some(moves), ..
+                            } => score = Some(Score::from_mate(*moves)),
+                            _ => (),
+                        }
+                    }
+
+                    if let (Some(chess_move), Some(score)) = (first_move, score) {
+                        line_scores.insert(chess_move, score);
+                    }
+                }
+
+                // Ignore unknown or unexpected messages
+                _ => (),
+            }
+        }
+
+        self.set_multipv(1)?;
+        Ok(line_scores)
+    }
+}
+
+/// Reads a move from the backend, which writes castling as the king taking its rook in Chess960.
+fn read_move(board: &Board, chess_move: ChessMove, limits: SearchLimits) -> ChessMove {
+    match limits.chess960 {
+        Some(_) => chess960::from_chess960(board, chess_move),
+        None => chess_move,
+    }
+}
+
+impl Drop for BackendProcess {
+    fn drop(&mut self) {
+        // Tell the backend to gracefully quit
+        //
+        // If this fails, it has likely already exited.
+        let _ = self.send(UciMessage::Quit);
+
+        let process = match self.process {
+            Some(ref mut process) => process,
+            None => return,
+        };
+
+        // Check if it's exited after a bit
+        thread::sleep(Duration::from_millis(10));
+        match process.try_wait() {
+            Ok(Some(status)) if status.success() => {
+                log!(self.log_file, "Backend exited successfully");
+            }
+            Ok(Some(_)) => log!(self.log_file, "Backend exited with errors"),
+            Err(error) => log!(self.log_file, "Backend has an unknown status: {}", error),
+            Ok(None) => {
+                log!(self.log_file, "Backend has not yet exited, killing");
+
+                // We don't care if this succeeds or not, just send the signal.
+                // We're done with it and are trying to clean up.
+                let _ = process.kill();
+                let _ = process.wait();
+            }
+        }
+    }
+}
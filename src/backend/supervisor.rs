@@ -0,0 +1,158 @@
+/*
+ * backend/supervisor.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Keeps a backend process alive, restarting it if it crashes or hangs.
+
+use super::process::BackendProcess;
+use super::{BackendCommand, BackendOption, Supervision};
+use crate::error::Result;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub struct Supervisor {
+    process: BackendProcess,
+    command: BackendCommand,
+    options: Vec<BackendOption>,
+    supervision: Supervision,
+    restarts: u32,
+    log_file: Arc<File>,
+}
+
+impl Supervisor {
+    // Constructor
+    pub fn spawn(
+        command: &BackendCommand,
+        options: &[BackendOption],
+        supervision: Supervision,
+        log_file: Arc<File>,
+    ) -> Result<Self> {
+        let process =
+            BackendProcess::spawn(command, options, supervision.timeout, Arc::clone(&log_file))?;
+
+        Ok(Supervisor {
+            process,
+            command: command.clone(),
+            options: options.to_vec(),
+            supervision,
+            restarts: 0,
+            log_file,
+        })
+    }
+
+    /// Replaces the backend process with a fresh one.
+    ///
+    /// The new process goes through the same handshake and receives the same options.
+    fn restart(&mut self) -> Result<()> {
+        self.restarts += 1;
+        log!(
+            self.log_file,
+            "Restarting backend engine (restart #{})",
+            self.restarts,
+        );
+
+        // Spawn first, so the old process is only dropped (and killed) on success
+        let process = BackendProcess::spawn(
+            &self.command,
+            &self.options,
+            self.supervision.timeout,
+            Arc::clone(&self.log_file),
+        )?;
+
+        self.process = process;
+        Ok(())
+    }
+
+    /// Runs an operation against the backend, restarting it and retrying on failure.
+    pub fn run<T, F>(&mut self, mut f: F) -> Result<T>
+    where
+        F: FnMut(&mut BackendProcess) -> Result<T>,
+    {
+        let mut retries = 0;
+
+        loop {
+            match f(&mut self.process) {
+                Err(error) if error.is_backend_failure() => {
+                    log!(self.log_file, "Backend engine failed: {}", error);
+
+                    if retries >= self.supervision.max_retries {
+                        log!(self.log_file, "Out of retries, giving up");
+                        return Err(error);
+                    }
+
+                    retries += 1;
+                    self.restart()?;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[test]
+fn restarts_and_retries() {
+    use super::mock::{Failure, MockScript};
+    use super::{SearchControl, SearchLimits};
+    use crate::config::Configuration;
+    use crate::engine::EngineKind;
+    use crate::error::Error;
+    use crate::score::ScoredMove;
+    use chess::Board;
+    use std::time::Duration;
+
+    // Searches with a limit, so a hung backend can be detected
+    let limits = SearchLimits {
+        nodes: Some(1000),
+        ..SearchLimits::default()
+    };
+
+    let search = |script: MockScript, max_retries| -> (Result<ScoredMove>, u32) {
+        let config = Configuration::mock(EngineKind::Stockfish, script);
+        let supervision = Supervision {
+            timeout: Some(Duration::from_millis(100)),
+            max_retries,
+        };
+
+        let mut supervisor = Supervisor::spawn(&config.backend, &[], supervision, config.log_file)
+            .expect("Unable to start backend");
+        let control = SearchControl::new();
+        let result = supervisor
+            .run(|process| process.search_position(&Board::default(), limits, &control))
+            .map(|(scored_move, _)| scored_move);
+
+        (result, supervisor.restarts)
+    };
+
+    // Each crash or hang is followed by a restart and another try
+    let (result, restarts) = search(MockScript::new().fail(Failure::Crash, 2), 2);
+    assert!(result.is_ok(), "Search failed: {:?}", result);
+    assert_eq!(restarts, 2);
+
+    let (result, restarts) = search(MockScript::new().fail(Failure::Hang, 1), 2);
+    assert!(result.is_ok(), "Search failed: {:?}", result);
+    assert_eq!(restarts, 1);
+
+    // Until it runs out of retries
+    let (result, restarts) = search(MockScript::new().fail(Failure::Crash, 3), 2);
+    assert!(matches!(result, Err(Error::BackendExited)), "{:?}", result);
+    assert_eq!(restarts, 2);
+
+    // The backend is still running after bad output, so it isn't restarted
+    let (result, restarts) = search(MockScript::new().fail(Failure::Malformed, 1), 2);
+    assert!(
+        matches!(result, Err(Error::BackendMissingScore)),
+        "{:?}",
+        result
+    );
+    assert_eq!(restarts, 0);
+}
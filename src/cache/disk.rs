@@ -0,0 +1,375 @@
+/*
+ * cache/disk.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Persistent cache of backend evaluations, shared across runs.
+//!
+//! The file is a header followed by fixed-size records, which are only ever
+//! appended. Each record is written with a single `write()` to a file opened
+//! in append mode, so records from concurrent processes don't overwrite each
+//! other. Every record carries a checksum, and when loading we skip over any
+//! bytes which don't form a valid record (such as a write cut short by a crash).
+//!
+//! Records written by other processes are picked up the next time the file is opened.
+//!
+//! Scores depend on which engine produced them and how it was set up, so the
+//! header holds a fingerprint of the backend command and its options. A file
+//! written with a different backend is refused, rather than mixing scores.
+//! Only standard chess evaluations are stored, never Chess960 or variants.
+//!
+//! Every record is loaded into memory when the file is opened, at roughly
+//! 100 bytes each, so a file of a million evaluations costs about 100 MB.
+//!
+//! Header (24 bytes):
+//! * Magic `MLRDEVAL`
+//! * Format version, `u32`
+//! * Record size, `u32`
+//! * Backend fingerprint (FNV-1a of its command and options), `u64`
+//!
+//! Record (48 bytes, all integers little-endian):
+//! * Board hash, `u64`
+//! * Node limit, `u64` (`u64::MAX` for none)
+//! * Time limit in nanoseconds, `u64` (`u64::MAX` for none)
+//! * Score kind, `u8`, then three bytes of padding
+//! * Score value, `i32`
+//! * Best move source square, destination square, and promotion piece, `u8` each
+//! * One byte of padding
+//! * Depth limit, `u8` (0 for none)
+//! * Seven bytes reserved, always zero
+//! * Checksum of the preceding bytes (FNV-1a), `u32`
+
+use super::CacheKey;
+use crate::backend::{BackendCommand, BackendOption};
+use crate::error::{Error, Result};
+use crate::score::{Score, ScoredMove};
+use chess::{ChessMove, ALL_PIECES, ALL_SQUARES};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+const MAGIC: &[u8; 8] = b"MLRDEVAL";
+const VERSION: u32 = 2;
+const HEADER_SIZE: usize = 24;
+const RECORD_SIZE: usize = 48;
+const NONE: u64 = u64::MAX;
+
+#[derive(Debug)]
+pub struct DiskCache {
+    file: File,
+    entries: HashMap<CacheKey, ScoredMove>,
+}
+
+impl DiskCache {
+    /// Opens the cache file, creating it if it doesn't exist, and loads all valid records.
+    ///
+    /// The file must have been written by the same backend setup.
+    pub fn open(path: &Path, command: &BackendCommand, options: &[BackendOption]) -> Result<Self> {
+        let header = header(fingerprint(command, options));
+        let mut file = match OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create_new(true)
+            .open(path)
+        {
+            // We created the file, so we write the header
+            Ok(mut file) => {
+                file.write_all(&header)?;
+                file
+            }
+
+            // Someone else created the file, so we load it
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                OpenOptions::new().read(true).append(true).open(path)?
+            }
+
+            Err(error) => return Err(Error::Io(error)),
+        };
+
+        let data = read_all(&mut file)?;
+        if data[..MAGIC.len()] != MAGIC[..] {
+            return Err(Error::InvalidCache("not an evaluation cache file"));
+        }
+
+        if data[MAGIC.len()..16] != header[MAGIC.len()..16] {
+            return Err(Error::InvalidCache("unsupported cache file version"));
+        }
+
+        if data[16..HEADER_SIZE] != header[16..] {
+            return Err(Error::InvalidCache(
+                "cache file was written by a different backend or backend options",
+            ));
+        }
+
+        let entries = parse_records(&data[HEADER_SIZE..]);
+        Ok(DiskCache { file, entries })
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn get(&self, key: &CacheKey) -> Option<ScoredMove> {
+        self.entries.get(key).copied()
+    }
+
+    /// Appends an evaluation to the cache file.
+    pub fn insert(&mut self, key: CacheKey, scored_move: ScoredMove) -> Result<()> {
+        if self.entries.contains_key(&key) {
+            return Ok(());
+        }
+
+        // Must be a single write, so it is appended atomically
+        let record = encode_record(&key, &scored_move);
+        self.file.write_all(&record)?;
+        self.entries.insert(key, scored_move);
+        Ok(())
+    }
+}
+
+fn header(fingerprint: u64) -> [u8; HEADER_SIZE] {
+    let mut header = [0; HEADER_SIZE];
+    header[..8].copy_from_slice(MAGIC);
+    header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+    header[12..16].copy_from_slice(&(RECORD_SIZE as u32).to_le_bytes());
+    header[16..24].copy_from_slice(&fingerprint.to_le_bytes());
+    header
+}
+
+/// Identifies the backend setup, from everything which could change its scores.
+fn fingerprint(command: &BackendCommand, options: &[BackendOption]) -> u64 {
+    let mut text = format!("{}\0", command.program.to_string_lossy());
+
+    for arg in &command.args {
+        text.push_str(&format!("arg {}\0", arg.to_string_lossy()));
+    }
+
+    if let Some(ref working_dir) = command.working_dir {
+        text.push_str(&format!("dir {}\0", working_dir.display()));
+    }
+
+    for option in options {
+        text.push_str(&format!("option {}={}\0", option.name, option.value));
+    }
+
+    fnv1a_64(text.as_bytes())
+}
+
+/// Reads the whole file, waiting briefly if another process is still writing the header.
+fn read_all(file: &mut File) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+
+    for _ in 0..10 {
+        data.clear();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut data)?;
+
+        if data.len() >= HEADER_SIZE {
+            return Ok(data);
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    Err(Error::InvalidCache("cache file header is missing"))
+}
+
+fn parse_records(mut data: &[u8]) -> HashMap<CacheKey, ScoredMove> {
+    let mut entries = HashMap::new();
+
+    while data.len() >= RECORD_SIZE {
+        match decode_record(&data[..RECORD_SIZE]) {
+            Some((key, scored_move)) => {
+                entries.insert(key, scored_move);
+                data = &data[RECORD_SIZE..];
+            }
+
+            // Not a valid record here, resynchronize one byte later
+            None => data = &data[1..],
+        }
+    }
+
+    entries
+}
+
+fn encode_record(key: &CacheKey, scored_move: &ScoredMove) -> [u8; RECORD_SIZE] {
+    let (score_kind, score_value) = match scored_move.score {
+        Score::Centipawns(value) => (0, value),
+        Score::OurMate(moves) => (1, i32::from(moves)),
+        Score::TheirMate(moves) => (2, i32::from(moves)),
+        Score::Stalemate(moves) => (3, i32::from(moves)),
+    };
+
+    let chess_move = scored_move.chess_move;
+    let promotion = chess_move
+        .get_promotion()
+        .map_or(0, |piece| piece.to_index() as u8 + 1);
+
+    let nodes = key.nodes.unwrap_or(NONE);
+    let move_time = key
+        .move_time
+        .map_or(NONE, |move_time| move_time.as_nanos() as u64);
+
+    let mut record = [0; RECORD_SIZE];
+    record[0..8].copy_from_slice(&key.hash.to_le_bytes());
+    record[8..16].copy_from_slice(&nodes.to_le_bytes());
+    record[16..24].copy_from_slice(&move_time.to_le_bytes());
+    record[24] = score_kind;
+    record[28..32].copy_from_slice(&score_value.to_le_bytes());
+    record[32] = chess_move.get_source().to_int();
+    record[33] = chess_move.get_dest().to_int();
+    record[34] = promotion;
+    record[36] = key.depth.unwrap_or(0);
+
+    let checksum = fnv1a(&record[..RECORD_SIZE - 4]);
+    record[RECORD_SIZE - 4..].copy_from_slice(&checksum.to_le_bytes());
+    record
+}
+
+fn decode_record(record: &[u8]) -> Option<(CacheKey, ScoredMove)> {
+    let read_u64 = |offset: usize| {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&record[offset..offset + 8]);
+        u64::from_le_bytes(bytes)
+    };
+
+    let read_u32 = |offset: usize| {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&record[offset..offset + 4]);
+        u32::from_le_bytes(bytes)
+    };
+
+    if fnv1a(&record[..RECORD_SIZE - 4]) != read_u32(RECORD_SIZE - 4) {
+        return None;
+    }
+
+    let key = CacheKey {
+        hash: read_u64(0),
+        nodes: Some(read_u64(8)).filter(|&nodes| nodes != NONE),
+        move_time: Some(read_u64(16))
+            .filter(|&nanos| nanos != NONE)
+            .map(Duration::from_nanos),
+        depth: Some(record[36]).filter(|&depth| depth != 0),
+        lines: 1,
+        chess960: None,
+    };
+
+    let score_value = read_u32(28) as i32;
+    let mate_moves = u8::try_from(score_value).ok();
+    let score = match record[24] {
+        0 => Score::Centipawns(score_value),
+        1 => Score::OurMate(mate_moves?),
+        2 => Score::TheirMate(mate_moves?),
+        3 => Score::Stalemate(mate_moves?),
+        _ => return None,
+    };
+
+    let source = *ALL_SQUARES.get(usize::from(record[32]))?;
+    let dest = *ALL_SQUARES.get(usize::from(record[33]))?;
+    let promotion = match record[34] {
+        0 => None,
+        index => Some(*ALL_PIECES.get(usize::from(index) - 1)?),
+    };
+
+    let chess_move = ChessMove::new(source, dest, promotion);
+    Some((key, ScoredMove { chess_move, score }))
+}
+
+/// 32-bit FNV-1a hash, used as a record checksum.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// 64-bit FNV-1a hash, used for the backend fingerprint.
+fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[test]
+fn round_trip() {
+    use chess::{Piece, Square};
+
+    let path =
+        std::env::temp_dir().join(format!("mallard-chess-test-{}.cache", std::process::id(),));
+    let _ = std::fs::remove_file(&path);
+
+    let first_key = CacheKey {
+        hash: 0xdead_beef,
+        nodes: Some(10_000),
+        move_time: None,
+        depth: Some(12),
+        lines: 1,
+        chess960: None,
+    };
+    let first_move = ScoredMove {
+        chess_move: ChessMove::new(Square::A7, Square::A8, Some(Piece::Queen)),
+        score: Score::Centipawns(-250),
+    };
+
+    let second_key = CacheKey {
+        hash: 0x1234,
+        nodes: None,
+        move_time: Some(Duration::from_micros(33_333)),
+        depth: None,
+        lines: 1,
+        chess960: None,
+    };
+    let second_move = ScoredMove {
+        chess_move: ChessMove::new(Square::E2, Square::E4, None),
+        score: Score::TheirMate(3),
+    };
+
+    let command = BackendCommand::default();
+
+    {
+        let mut cache = DiskCache::open(&path, &command, &[]).expect("Unable to create cache");
+        cache
+            .insert(first_key, first_move)
+            .expect("Unable to write");
+
+        // Simulate a write cut short by a crash, followed by another process appending
+        cache.file.write_all(&[0xff; 13]).expect("Unable to write");
+        cache
+            .insert(second_key, second_move)
+            .expect("Unable to write");
+    }
+
+    let cache = DiskCache::open(&path, &command, &[]).expect("Unable to open cache");
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(&first_key), Some(first_move));
+    assert_eq!(cache.get(&second_key), Some(second_move));
+
+    // Scores from another backend setup aren't mixed in
+    let options = ["Skill Level=5".parse().expect("Invalid option")];
+    assert!(matches!(
+        DiskCache::open(&path, &command, &options),
+        Err(Error::InvalidCache(_)),
+    ));
+
+    let other_command = BackendCommand {
+        args: vec!["--weights=small".into()],
+        ..BackendCommand::default()
+    };
+    assert!(matches!(
+        DiskCache::open(&path, &other_command, &[]),
+        Err(Error::InvalidCache(_)),
+    ));
+
+    std::fs::remove_file(&path).expect("Unable to remove cache");
+}
@@ -0,0 +1,300 @@
+/*
+ * cache/mod.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! In-memory cache of backend evaluations.
+//!
+//! Engines like Worstfish evaluate the same positions again and again,
+//! across turns and through transpositions. Since a search with the same
+//! limits should give the same result, we can skip asking the backend.
+//!
+//! Optionally, single-position evaluations are also kept in a file
+//! shared between runs, see `DiskCache`.
+//!
+//! Antichess evaluations are never cached, since scores in variants
+//! aren't comparable with regular chess.
+
+mod disk;
+
+pub use self::disk::DiskCache;
+
+use crate::chess960::Castling;
+use crate::error::Result;
+use crate::score::ScoredMove;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Identifies a search: the position, and the limits it was searched with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Zobrist hash of the board.
+    pub hash: u64,
+
+    /// Node limit for the search.
+    pub nodes: Option<u64>,
+
+    /// Time limit for the search.
+    pub move_time: Option<Duration>,
+
+    /// Depth limit for the search.
+    pub depth: Option<u8>,
+
+    /// Number of lines searched, that is, the `MultiPV` value.
+    pub lines: usize,
+
+    /// Castling rights when playing Chess960, which the board hash doesn't cover.
+    pub chess960: Option<Castling>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    scored_moves: Vec<ScoredMove>,
+    last_used: u64,
+}
+
+/// A size-bounded map of search results, evicting the least recently used.
+#[derive(Debug)]
+pub struct EvaluationCache {
+    entries: HashMap<CacheKey, CacheEntry>,
+    recency: BTreeMap<u64, CacheKey>,
+    capacity: usize,
+    disk: Option<DiskCache>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl EvaluationCache {
+    /// Creates a cache holding at most `capacity` entries.
+    ///
+    /// A capacity of zero disables caching in memory, though a disk cache is still used.
+    pub fn new(capacity: usize) -> Self {
+        EvaluationCache {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            capacity,
+            disk: None,
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Adds a persistent cache, which is checked on a miss in memory.
+    pub fn with_disk(mut self, disk: DiskCache) -> Self {
+        self.disk = Some(disk);
+        self
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    #[inline]
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Looks up a previous search result, marking it as recently used.
+    pub fn get(&mut self, key: &CacheKey) -> Option<Vec<ScoredMove>> {
+        self.clock += 1;
+
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.last_used);
+            self.recency.insert(self.clock, *key);
+            entry.last_used = self.clock;
+            self.hits += 1;
+            return Some(entry.scored_moves.clone());
+        }
+
+        // Promote evaluations from disk into memory, if there's any room there
+        match self.disk.as_ref().and_then(|disk| disk.get(key)) {
+            Some(scored_move) => {
+                self.insert_memory(*key, vec![scored_move]);
+                self.hits += 1;
+                Some(vec![scored_move])
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Stores a search result.
+    ///
+    /// This only fails if the result couldn't be written to disk,
+    /// in which case it is still cached in memory.
+    pub fn insert(&mut self, key: CacheKey, scored_moves: Vec<ScoredMove>) -> Result<()> {
+        let disk_entry = match (&scored_moves[..], &mut self.disk) {
+            // The file only holds single evaluations from standard chess
+            (&[scored_move], Some(disk)) if key.lines == 1 && key.chess960.is_none() => {
+                Some((disk, scored_move))
+            }
+            _ => None,
+        };
+
+        let result = match disk_entry {
+            Some((disk, scored_move)) => disk.insert(key, scored_move),
+            None => Ok(()),
+        };
+
+        self.insert_memory(key, scored_moves);
+        result
+    }
+
+    /// Stores a search result in memory, evicting the least recently used if we're full.
+    fn insert_memory(&mut self, key: CacheKey, scored_moves: Vec<ScoredMove>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.clock += 1;
+
+        let entry = CacheEntry {
+            scored_moves,
+            last_used: self.clock,
+        };
+
+        if let Some(old_entry) = self.entries.insert(key, entry) {
+            self.recency.remove(&old_entry.last_used);
+        }
+
+        self.recency.insert(self.clock, key);
+
+        while self.entries.len() > self.capacity {
+            let (&last_used, &oldest_key) = self
+                .recency
+                .iter()
+                .next()
+                .expect("Recency list empty while cache over capacity");
+
+            self.recency.remove(&last_used);
+            self.entries.remove(&oldest_key);
+        }
+    }
+}
+
+#[test]
+fn eviction() {
+    use crate::score::Score;
+    use chess::{ChessMove, Square};
+
+    let key = |hash| CacheKey {
+        hash,
+        nodes: Some(1000),
+        move_time: None,
+        depth: None,
+        lines: 1,
+        chess960: None,
+    };
+
+    let value = |centipawns| {
+        vec![ScoredMove {
+            chess_move: ChessMove::new(Square::E2, Square::E4, None),
+            score: Score::Centipawns(centipawns),
+        }]
+    };
+
+    let mut cache = EvaluationCache::new(2);
+    cache
+        .insert(key(1), value(10))
+        .expect("Unable to insert into cache");
+    cache
+        .insert(key(2), value(20))
+        .expect("Unable to insert into cache");
+
+    // Use the first entry, so the second is the least recently used
+    assert_eq!(cache.get(&key(1)), Some(value(10)));
+
+    cache
+        .insert(key(3), value(30))
+        .expect("Unable to insert into cache");
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(&key(2)), None);
+    assert_eq!(cache.get(&key(1)), Some(value(10)));
+    assert_eq!(cache.get(&key(3)), Some(value(30)));
+
+    // Different search limits are a different entry
+    let other_limits = CacheKey {
+        nodes: None,
+        ..key(1)
+    };
+    assert_eq!(cache.get(&other_limits), None);
+
+    // So are the same position and limits in Chess960
+    let chess960 = CacheKey {
+        chess960: Some(Castling::default()),
+        ..key(1)
+    };
+    assert_eq!(cache.get(&chess960), None);
+
+    // Replacing an entry doesn't grow the cache
+    cache
+        .insert(key(3), value(-30))
+        .expect("Unable to insert into cache");
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(&key(3)), Some(value(-30)));
+    assert_eq!((cache.hits(), cache.misses()), (4, 3));
+
+    // Disabled cache
+    let mut cache = EvaluationCache::new(0);
+    cache
+        .insert(key(1), value(10))
+        .expect("Unable to insert into cache");
+    assert_eq!(cache.get(&key(1)), None);
+}
+
+#[test]
+fn disk_without_memory() {
+    use crate::backend::BackendCommand;
+    use crate::score::Score;
+    use chess::{ChessMove, Square};
+
+    let path = std::env::temp_dir().join(format!(
+        "mallard-chess-test-memoryless-{}.cache",
+        std::process::id(),
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let key = CacheKey {
+        hash: 0xabcd,
+        nodes: Some(1000),
+        move_time: None,
+        depth: None,
+        lines: 1,
+        chess960: None,
+    };
+    let value = vec![ScoredMove {
+        chess_move: ChessMove::new(Square::E2, Square::E4, None),
+        score: Score::Centipawns(15),
+    }];
+
+    // With no room in memory, evaluations are still read back from the file
+    let disk =
+        DiskCache::open(&path, &BackendCommand::default(), &[]).expect("Unable to open cache");
+    let mut cache = EvaluationCache::new(0).with_disk(disk);
+    cache
+        .insert(key, value.clone())
+        .expect("Unable to insert into cache");
+
+    assert_eq!(cache.get(&key), Some(value));
+    assert_eq!(cache.len(), 0);
+    assert_eq!((cache.hits(), cache.misses()), (1, 0));
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,581 @@
+/*
+ * chess960.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Castling for Chess960 (Fischer Random).
+//!
+//! The chess crate can only castle with the king on the e-file and the rooks
+//! in the corners. In Chess960 either may start elsewhere, so we keep track of
+//! castling rights ourselves, and make the castling moves it can't.
+//!
+//! Those moves are written as the king capturing its own rook, which is how
+//! UCI sends castling in Chess960 mode. Castling from the usual squares is
+//! left to the chess crate (as `e1g1`), and only translated when talking to
+//! the GUI or backend.
+
+use crate::error::{Error, Result};
+use chess::{
+    get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_rook_moves, BitBoard,
+    Board, BoardBuilder, CastleRights, ChessMove, Color, File, Piece, Rank, Square, EMPTY,
+    NUM_COLORS,
+};
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// Which side of the king a rook is castling from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Side {
+    King,
+    Queen,
+}
+
+impl Side {
+    const ALL: [Side; 2] = [Side::King, Side::Queen];
+
+    #[inline]
+    fn index(self) -> usize {
+        match self {
+            Side::King => 0,
+            Side::Queen => 1,
+        }
+    }
+
+    /// Where the king ends up after castling.
+    #[inline]
+    fn king_file(self) -> File {
+        match self {
+            Side::King => File::G,
+            Side::Queen => File::C,
+        }
+    }
+
+    /// Where the rook ends up after castling.
+    #[inline]
+    fn rook_file(self) -> File {
+        match self {
+            Side::King => File::F,
+            Side::Queen => File::D,
+        }
+    }
+
+    /// The file the rook starts on in regular chess.
+    #[inline]
+    fn corner(self) -> File {
+        match self {
+            Side::King => File::H,
+            Side::Queen => File::A,
+        }
+    }
+}
+
+/// The files of the rooks each side may still castle with.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Castling {
+    rooks: [[Option<File>; 2]; NUM_COLORS],
+
+    /// Where each king started, so we can tell when it has moved away.
+    kings: [Option<File>; NUM_COLORS],
+}
+
+impl Castling {
+    /// The castling rights the chess crate already tracks for this board.
+    pub fn from_board(board: &Board) -> Self {
+        let mut castling = Castling::default();
+
+        for &color in &[Color::White, Color::Black] {
+            let rights = board.castle_rights(color);
+
+            if rights.has_kingside() {
+                castling.set(color, Side::King, Some(File::H));
+            }
+
+            if rights.has_queenside() {
+                castling.set(color, Side::Queen, Some(File::A));
+            }
+
+            if rights != CastleRights::NoRights {
+                castling.kings[color.to_index()] = Some(File::E);
+            }
+        }
+
+        castling
+    }
+
+    #[inline]
+    pub fn rook(&self, color: Color, side: Side) -> Option<File> {
+        self.rooks[color.to_index()][side.index()]
+    }
+
+    #[inline]
+    fn set(&mut self, color: Color, side: Side, file: Option<File>) {
+        self.rooks[color.to_index()][side.index()] = file;
+    }
+
+    /// Only keeps the rights whose king and rook are still in place on this board.
+    ///
+    /// This is exact for any position reached by a single move, which is
+    /// how far ahead the backend is asked about.
+    fn filter(&self, board: &Board) -> Self {
+        let mut castling = *self;
+
+        for &color in &[Color::White, Color::Black] {
+            for &side in &Side::ALL {
+                if let Some(file) = self.rook(color, side) {
+                    if self.squares(board, color, file).is_none() {
+                        castling.set(color, side, None);
+                    }
+                }
+            }
+        }
+
+        castling
+    }
+
+    /// Updates the rights for a move about to be made on `board`.
+    pub fn update(&mut self, board: &Board, chess_move: ChessMove) {
+        let color = board.side_to_move();
+
+        if board.piece_on(chess_move.get_source()) == Some(Piece::King) {
+            self.rooks[color.to_index()] = [None; 2];
+        }
+
+        // Moving a rook, or having it captured, loses that right
+        for &color in &[Color::White, Color::Black] {
+            for &side in &Side::ALL {
+                if let Some(file) = self.rook(color, side) {
+                    let square = Square::make_square(back_rank(color), file);
+
+                    if chess_move.get_source() == square || chess_move.get_dest() == square {
+                        self.set(color, side, None);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lists the castling moves which the chess crate can't make itself.
+    pub fn moves(&self, board: &Board) -> Vec<ChessMove> {
+        let color = board.side_to_move();
+
+        Side::ALL
+            .iter()
+            .filter_map(|&side| {
+                let file = self.rook(color, side)?;
+                let (king, rook) = self.squares(board, color, file)?;
+
+                // The chess crate handles castling from the regular squares
+                if king.get_file() == File::E && file == side.corner() {
+                    return None;
+                }
+
+                let chess_move = ChessMove::new(king, rook, None);
+                if is_legal_castle(board, chess_move) {
+                    Some(chess_move)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Whether a move is legal, including castling moves the chess crate can't make.
+    pub fn is_legal(&self, board: &Board, chess_move: ChessMove) -> bool {
+        board.legal(chess_move) || self.moves(board).contains(&chess_move)
+    }
+
+    /// Finds the move which castles to this side, as xboard only says `O-O` or `O-O-O`.
+    pub fn castle_move(&self, board: &Board, side: Side) -> Option<ChessMove> {
+        let color = board.side_to_move();
+        let file = self.rook(color, side)?;
+        let (king, rook) = self.squares(board, color, file)?;
+        Some(from_chess960(board, ChessMove::new(king, rook, None)))
+    }
+
+    /// The king and rook squares for castling with the rook on this file, if they're both in place.
+    fn squares(&self, board: &Board, color: Color, file: File) -> Option<(Square, Square)> {
+        let rank = back_rank(color);
+        let king = board.king_square(color);
+        let rook = Square::make_square(rank, file);
+
+        if king.get_rank() == rank
+            && Some(king.get_file()) == self.kings[color.to_index()]
+            && board.piece_on(rook) == Some(Piece::Rook)
+            && board.color_on(rook) == Some(color)
+        {
+            Some((king, rook))
+        } else {
+            None
+        }
+    }
+
+    /// Writes the castling field of a FEN, naming the rook files as in Shredder-FEN.
+    pub fn fen_field(&self, board: &Board) -> String {
+        let castling = self.filter(board);
+        let mut field = String::new();
+
+        for &color in &[Color::White, Color::Black] {
+            for &side in &Side::ALL {
+                if let Some(file) = castling.rook(color, side) {
+                    let letter = (b'A' + file.to_index() as u8) as char;
+
+                    field.push(match color {
+                        Color::White => letter,
+                        Color::Black => letter.to_ascii_lowercase(),
+                    });
+                }
+            }
+        }
+
+        if field.is_empty() {
+            field.push('-');
+        }
+
+        field
+    }
+
+    /// Writes the board as a Shredder-FEN, with these castling rights.
+    pub fn fen(&self, board: &Board) -> String {
+        let fen = board.to_string();
+        let mut fields = fen.split(' ').map(String::from).collect::<Vec<_>>();
+        fields[2] = self.fen_field(board);
+        fields.join(" ")
+    }
+}
+
+/// Parses a FEN, which may use Shredder-FEN or X-FEN castling rights.
+///
+/// Castling rights the chess crate can handle are given to the board,
+/// while all of them are returned separately.
+pub fn parse_fen(fen: &str) -> Result<(Board, Castling)> {
+    let invalid = || Error::InvalidFen(fen.into());
+    let mut fields = fen.split_whitespace().map(String::from).collect::<Vec<_>>();
+    if fields.len() < 4 {
+        return Err(invalid());
+    }
+
+    // Read the pieces first, without any castling rights
+    let castling_field = std::mem::replace(&mut fields[2], "-".into());
+    let board = Board::from_str(&fields.join(" ")).map_err(|_| invalid())?;
+    let mut castling = Castling::default();
+
+    for letter in castling_field.chars().filter(|&letter| letter != '-') {
+        let color = if letter.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+
+        let king = board.king_square(color);
+        if king.get_rank() != back_rank(color) {
+            return Err(invalid());
+        }
+
+        let file = match letter.to_ascii_uppercase() {
+            // X-FEN means the outermost rook on that side
+            'K' => outermost_rook(&board, color, Side::King),
+            'Q' => outermost_rook(&board, color, Side::Queen),
+            letter @ 'A'..='H' => Some(File::from_index((letter as u8 - b'A') as usize)),
+            _ => None,
+        }
+        .ok_or_else(invalid)?;
+
+        if board.piece_on(Square::make_square(back_rank(color), file)) != Some(Piece::Rook) {
+            return Err(invalid());
+        }
+
+        let side = if file > king.get_file() {
+            Side::King
+        } else {
+            Side::Queen
+        };
+
+        castling.set(color, side, Some(file));
+        castling.kings[color.to_index()] = Some(king.get_file());
+    }
+
+    // Now hand the chess crate whatever it can handle itself
+    let mut builder = BoardBuilder::from(board);
+    for &color in &[Color::White, Color::Black] {
+        let regular = |side: Side| {
+            board.king_square(color).get_file() == File::E
+                && castling.rook(color, side) == Some(side.corner())
+        };
+
+        let rights = match (regular(Side::King), regular(Side::Queen)) {
+            (true, true) => CastleRights::Both,
+            (true, false) => CastleRights::KingSide,
+            (false, true) => CastleRights::QueenSide,
+            (false, false) => CastleRights::NoRights,
+        };
+
+        builder.castle_rights(color, rights);
+    }
+
+    let board = Board::try_from(builder).map_err(|_| invalid())?;
+    Ok((board, castling))
+}
+
+/// Whether this move is castling, written as the king capturing its own rook.
+#[inline]
+pub fn is_castle(board: &Board, chess_move: ChessMove) -> bool {
+    board.piece_on(chess_move.get_source()) == Some(Piece::King)
+        && board.color_on(chess_move.get_dest()) == Some(board.side_to_move())
+}
+
+/// Makes a move, including castling moves which the chess crate can't make.
+pub fn make_move(board: &Board, chess_move: ChessMove) -> Board {
+    if !is_castle(board, chess_move) {
+        return board.make_move_new(chess_move);
+    }
+
+    let color = board.side_to_move();
+    let (king, rook) = (chess_move.get_source(), chess_move.get_dest());
+    let side = castle_side(king, rook);
+    let rank = back_rank(color);
+
+    let mut builder = BoardBuilder::from(board);
+    builder
+        .clear_square(king)
+        .clear_square(rook)
+        .piece(
+            Square::make_square(rank, side.king_file()),
+            Piece::King,
+            color,
+        )
+        .piece(
+            Square::make_square(rank, side.rook_file()),
+            Piece::Rook,
+            color,
+        )
+        .castle_rights(color, CastleRights::NoRights)
+        .side_to_move(!color)
+        .en_passant(None);
+
+    Board::try_from(builder).expect("Castling produced an invalid board")
+}
+
+/// Rewrites the chess crate's castling moves as the king capturing its rook.
+///
+/// Moves already written that way are left alone, even when the rook
+/// stands where the king would land after a regular castle.
+pub fn to_chess960(board: &Board, chess_move: ChessMove) -> ChessMove {
+    let (source, dest) = (chess_move.get_source(), chess_move.get_dest());
+    let is_regular_castle = board.piece_on(source) == Some(Piece::King)
+        && board.piece_on(dest).is_none()
+        && source.get_file() == File::E
+        && source.get_rank() == dest.get_rank();
+
+    let rook_file = match (is_regular_castle, dest.get_file()) {
+        (true, File::G) => File::H,
+        (true, File::C) => File::A,
+        _ => return chess_move,
+    };
+
+    ChessMove::new(
+        source,
+        Square::make_square(source.get_rank(), rook_file),
+        None,
+    )
+}
+
+/// Rewrites castling from the regular squares the way the chess crate expects it.
+pub fn from_chess960(board: &Board, chess_move: ChessMove) -> ChessMove {
+    let (source, dest) = (chess_move.get_source(), chess_move.get_dest());
+    if !is_castle(board, chess_move) || source.get_file() != File::E {
+        return chess_move;
+    }
+
+    let side = castle_side(source, dest);
+    if dest.get_file() != side.corner() {
+        return chess_move;
+    }
+
+    ChessMove::new(
+        source,
+        Square::make_square(source.get_rank(), side.king_file()),
+        None,
+    )
+}
+
+/// Which side a castling move is towards, given in either notation.
+pub fn castle_direction(board: &Board, chess_move: ChessMove) -> Option<Side> {
+    let chess_move = to_chess960(board, chess_move);
+
+    if is_castle(board, chess_move) {
+        Some(castle_side(chess_move.get_source(), chess_move.get_dest()))
+    } else {
+        None
+    }
+}
+
+// Helpers
+
+#[inline]
+fn back_rank(color: Color) -> Rank {
+    match color {
+        Color::White => Rank::First,
+        Color::Black => Rank::Eighth,
+    }
+}
+
+#[inline]
+fn castle_side(king: Square, rook: Square) -> Side {
+    if rook.get_file() > king.get_file() {
+        Side::King
+    } else {
+        Side::Queen
+    }
+}
+
+fn outermost_rook(board: &Board, color: Color, side: Side) -> Option<File> {
+    let king_file = board.king_square(color).get_file().to_index();
+    let files = match side {
+        Side::King => (king_file + 1..8).rev().collect::<Vec<_>>(),
+        Side::Queen => (0..king_file).collect(),
+    };
+
+    files.into_iter().map(File::from_index).find(|&file| {
+        let square = Square::make_square(back_rank(color), file);
+        board.piece_on(square) == Some(Piece::Rook) && board.color_on(square) == Some(color)
+    })
+}
+
+/// All the squares between two on the same rank, including both ends.
+fn span(rank: Rank, from: File, to: File) -> BitBoard {
+    let (low, high) = if from <= to { (from, to) } else { (to, from) };
+
+    (low.to_index()..=high.to_index())
+        .map(|file| BitBoard::from_square(Square::make_square(rank, File::from_index(file))))
+        .fold(EMPTY, |squares, square| squares | square)
+}
+
+/// Whether the given side attacks this square, with these pieces blocking.
+fn is_attacked(board: &Board, square: Square, by: Color, blockers: BitBoard) -> bool {
+    let theirs = *board.color_combined(by);
+    let pieces = |piece| *board.pieces(piece) & theirs;
+    let queens = pieces(Piece::Queen);
+
+    let attackers = (get_rook_moves(square, blockers) & (pieces(Piece::Rook) | queens))
+        | (get_bishop_moves(square, blockers) & (pieces(Piece::Bishop) | queens))
+        | (get_knight_moves(square) & pieces(Piece::Knight))
+        | (get_king_moves(square) & pieces(Piece::King))
+        | get_pawn_attacks(square, !by, pieces(Piece::Pawn));
+
+    attackers != EMPTY
+}
+
+fn is_legal_castle(board: &Board, chess_move: ChessMove) -> bool {
+    let color = board.side_to_move();
+    let rank = back_rank(color);
+    let (king, rook) = (chess_move.get_source(), chess_move.get_dest());
+    let side = castle_side(king, rook);
+
+    if *board.checkers() != EMPTY {
+        return false;
+    }
+
+    // Everything the king and rook pass over must be empty, besides themselves
+    let king_path = span(rank, king.get_file(), side.king_file());
+    let rook_path = span(rank, rook.get_file(), side.rook_file());
+    let others = *board.combined() ^ BitBoard::from_square(king) ^ BitBoard::from_square(rook);
+    if (king_path | rook_path) & others != EMPTY {
+        return false;
+    }
+
+    // And the king can't pass through check
+    let blockers = *board.combined() ^ BitBoard::from_square(king);
+    if king_path
+        .into_iter()
+        .any(|square| is_attacked(board, square, !color, blockers))
+    {
+        return false;
+    }
+
+    // Finally, the rook may have been shielding the king from a rook or queen
+    let king_dest = Square::make_square(rank, side.king_file());
+    let rook_dest = Square::make_square(rank, side.rook_file());
+    let blockers = others | BitBoard::from_square(king_dest) | BitBoard::from_square(rook_dest);
+    !is_attacked(board, king_dest, !color, blockers)
+}
+
+#[test]
+fn shredder_fen() {
+    // Kings on b1 and b8, with rooks on a and c
+    let fen = "rkrnbbqn/pppppppp/8/8/8/8/PPPPPPPP/RKRNBBQN w CAca - 0 1";
+    let (board, castling) = parse_fen(fen).expect("Invalid FEN");
+
+    assert_eq!(castling.rook(Color::White, Side::King), Some(File::C));
+    assert_eq!(castling.rook(Color::White, Side::Queen), Some(File::A));
+    assert_eq!(board.castle_rights(Color::White), CastleRights::NoRights);
+    assert_eq!(castling.fen(&board), fen);
+
+    // X-FEN says the same thing with the usual letters
+    let (_, x_castling) =
+        parse_fen("rkrnbbqn/pppppppp/8/8/8/8/PPPPPPPP/RKRNBBQN w KQkq - 0 1").expect("Invalid FEN");
+    assert_eq!(x_castling, castling);
+
+    // Regular positions are left to the chess crate
+    let (board, castling) = parse_fen(&Board::default().to_string()).expect("Invalid FEN");
+    assert_eq!(board, Board::default());
+    assert_eq!(castling, Castling::from_board(&board));
+    assert!(castling.moves(&board).is_empty());
+}
+
+#[test]
+fn castling_moves() {
+    let (board, mut castling) =
+        parse_fen("rk5r/8/8/8/8/8/8/RK2R3 w EAha - 0 1").expect("Invalid FEN");
+
+    // Kingside puts the king on g1 and the rook on f1, queenside the king on c1 and the rook on d1
+    let moves = castling.moves(&board);
+    assert_eq!(moves.len(), 2);
+
+    let kingside = ChessMove::from_str("b1e1").expect("Invalid move");
+    assert!(moves.contains(&kingside));
+    assert_eq!(castle_direction(&board, kingside), Some(Side::King));
+
+    castling.update(&board, kingside);
+    let board = make_move(&board, kingside);
+    assert_eq!(board.piece_on(Square::G1), Some(Piece::King));
+    assert_eq!(board.piece_on(Square::F1), Some(Piece::Rook));
+    assert_eq!(castling.fen_field(&board), "ha");
+
+    // A rook on the back rank stops the king from castling through check
+    let (board, castling) = parse_fen("1k6/8/8/8/8/8/8/RK2R2r w EA - 0 1").expect("Invalid FEN");
+    assert_eq!(
+        castling.moves(&board),
+        vec![ChessMove::from_str("b1a1").expect("Invalid move")],
+    );
+
+    // Regular castling is translated both ways
+    let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").expect("Invalid FEN");
+    let chess_move = ChessMove::from_str("e1g1").expect("Invalid move");
+    let translated = to_chess960(&board, chess_move);
+    assert_eq!(translated.to_string(), "e1h1");
+    assert_eq!(from_chess960(&board, translated), chess_move);
+}
+
+#[test]
+fn castling_onto_regular_squares() {
+    // The king starts on e1, but the rooks stand where it would land in regular chess
+    let (board, castling) =
+        parse_fen("1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1").expect("Invalid FEN");
+
+    let kingside = ChessMove::from_str("e1g1").expect("Invalid move");
+    let queenside = ChessMove::from_str("e1b1").expect("Invalid move");
+    assert!(castling.moves(&board).contains(&kingside));
+
+    // These are already written as the king taking its rook
+    assert_eq!(to_chess960(&board, kingside), kingside);
+    assert_eq!(to_chess960(&board, queenside), queenside);
+    assert_eq!(castle_direction(&board, kingside), Some(Side::King));
+    assert_eq!(castle_direction(&board, queenside), Some(Side::Queen));
+}
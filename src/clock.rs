@@ -0,0 +1,169 @@
+/*
+ * clock.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Time management for engine searches.
+//!
+//! Converts the time control sent by the GUI into a budget
+//! for how long we can spend deciding on the current move.
+
+use chess::Color;
+use std::cmp;
+use std::time::Duration;
+use vampirc_uci::UciTimeControl;
+
+/// How many moves to assume remain if the GUI doesn't say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Time reserved for process and pipe overhead, so we never flag.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// The smallest budget we will ever hand to a search.
+const MINIMUM_BUDGET: Duration = Duration::from_millis(10);
+
+/// The node limit for searches too short to time, which any engine finishes almost instantly.
+pub const LOW_TIME_NODES: u64 = 100;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct TimeControl {
+    /// Time remaining on our clock.
+    pub remaining: Option<Duration>,
+
+    /// Time added to our clock after each move.
+    pub increment: Option<Duration>,
+
+    /// Number of moves until the next time control.
+    pub moves_to_go: Option<u32>,
+
+    /// Exact time to spend on this move.
+    pub move_time: Option<Duration>,
+}
+
+impl TimeControl {
+    /// Reads the time control from a UCI `go` command, for the given side.
+    ///
+    /// Returns `None` for untimed searches, that is, `go ponder` and `go infinite`.
+    pub fn from_uci(time_control: &UciTimeControl, color: Color) -> Option<Self> {
+        match time_control {
+            UciTimeControl::Ponder | UciTimeControl::Infinite => None,
+            UciTimeControl::MoveTime(move_time) => Some(TimeControl {
+                move_time: to_std_duration(move_time),
+                ..TimeControl::default()
+            }),
+            UciTimeControl::TimeLeft {
+                white_time,
+                black_time,
+                white_increment,
+                black_increment,
+                moves_to_go,
+            } => {
+                let (remaining, increment) = match color {
+                    Color::White => (white_time, white_increment),
+                    Color::Black => (black_time, black_increment),
+                };
+
+                Some(TimeControl {
+                    remaining: remaining.as_ref().and_then(to_std_duration),
+                    increment: increment.as_ref().and_then(to_std_duration),
+                    moves_to_go: moves_to_go.map(u32::from),
+                    move_time: None,
+                })
+            }
+        }
+    }
+
+    /// Determines how long we may spend searching for this move.
+    ///
+    /// Returns `None` if there is no limit.
+    pub fn budget(&self) -> Option<Duration> {
+        if let Some(move_time) = self.move_time {
+            return Some(subtract_overhead(move_time));
+        }
+
+        let remaining = self.remaining?;
+        let increment = self.increment.unwrap_or_default();
+        let moves_to_go = cmp::max(self.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO), 1);
+
+        // Spend an even share of the remaining time, plus most of the increment,
+        // but never more than half of what is left on the clock.
+        let share = remaining / moves_to_go + increment * 3 / 4;
+        let budget = cmp::min(share, remaining / 2);
+
+        Some(subtract_overhead(budget))
+    }
+}
+
+/// Divides a search budget evenly between several searches.
+///
+/// Returns `None` if each search would get less than the minimum budget.
+/// Rounding every search up would overrun the budget as a whole, so
+/// these searches need to be limited some other way, such as by nodes.
+pub fn split_budget(budget: Duration, searches: usize) -> Option<Duration> {
+    let searches = u32::try_from(cmp::max(searches, 1)).unwrap_or(u32::MAX);
+    let share = budget / searches;
+
+    if share < MINIMUM_BUDGET {
+        None
+    } else {
+        Some(share)
+    }
+}
+
+fn subtract_overhead(duration: Duration) -> Duration {
+    cmp::max(duration.saturating_sub(MOVE_OVERHEAD), MINIMUM_BUDGET)
+}
+
+fn to_std_duration(duration: &vampirc_uci::Duration) -> Option<Duration> {
+    duration.to_std().ok()
+}
+
+#[test]
+fn budgets() {
+    let millis = Duration::from_millis;
+
+    // Fixed time per move
+    let time_control = TimeControl {
+        move_time: Some(millis(1000)),
+        ..TimeControl::default()
+    };
+    assert_eq!(time_control.budget(), Some(millis(950)));
+
+    // Sudden death, using the default number of moves to go
+    let time_control = TimeControl {
+        remaining: Some(millis(60_000)),
+        ..TimeControl::default()
+    };
+    assert_eq!(time_control.budget(), Some(millis(1950)));
+
+    // Increment, with few moves to go
+    let time_control = TimeControl {
+        remaining: Some(millis(10_000)),
+        increment: Some(millis(1000)),
+        moves_to_go: Some(2),
+        move_time: None,
+    };
+    assert_eq!(time_control.budget(), Some(millis(4950)));
+
+    // Almost out of time
+    let time_control = TimeControl {
+        remaining: Some(millis(20)),
+        ..TimeControl::default()
+    };
+    assert_eq!(time_control.budget(), Some(MINIMUM_BUDGET));
+
+    // No limit
+    assert_eq!(TimeControl::default().budget(), None);
+
+    // Splitting between searches
+    assert_eq!(split_budget(millis(3000), 30), Some(millis(100)));
+    assert_eq!(split_budget(millis(100), 30), None);
+    assert_eq!(split_budget(millis(100), 0), Some(millis(100)));
+}
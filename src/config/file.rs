@@ -0,0 +1,288 @@
+/*
+ * config/file.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! The optional TOML configuration file.
+//!
+//! It holds the same settings as the command line, grouped into
+//! `[backend]` and `[cache]` sections, plus an `[engine.<name>]`
+//! section for each engine. Anything given on the command line
+//! takes precedence over the file.
+//!
+//! Engines which use the backend may set their own `nodes` and
+//! `evaluation-mode`, which replace those in `[backend]` while
+//! that engine is chosen.
+//!
+//! Every section rejects keys it doesn't know, so typos are reported
+//! rather than silently ignored.
+
+use crate::engine::{EngineKind, Weights};
+use crate::error::{Error, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConfigFile {
+    pub log_file: Option<PathBuf>,
+    pub seed: Option<u64>,
+    pub protocol: Option<String>,
+    pub backend: BackendSection,
+    pub cache: CacheSection,
+    pub engine: EngineSections,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BackendSection {
+    pub path: Option<PathBuf>,
+    pub args: Option<Vec<String>>,
+    pub dir: Option<PathBuf>,
+    pub options: BTreeMap<String, OptionValue>,
+    pub timeout: Option<f32>,
+    pub retries: Option<u32>,
+    pub processes: Option<usize>,
+    pub nodes: Option<u64>,
+    pub evaluation_mode: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CacheSection {
+    pub size: Option<usize>,
+    pub file: Option<PathBuf>,
+}
+
+/// A value for a backend UCI option, which TOML lets be written without quotes.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum OptionValue {
+    Text(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+impl OptionValue {
+    pub fn to_uci(&self) -> String {
+        match self {
+            OptionValue::Text(value) => value.clone(),
+            OptionValue::Integer(value) => value.to_string(),
+            OptionValue::Boolean(value) => value.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineSections {
+    pub random: EmptySection,
+    pub pacifist: EmptySection,
+    pub stockfish: EngineSection,
+    pub mediocrefish: EngineSection,
+    pub drawfish: EngineSection,
+    pub worstfish: EngineSection,
+    pub scoville: ScovilleSection,
+    pub greedy: EmptySection,
+    pub alphabetical: AlphabeticalSection,
+    pub huddle: DistanceSection,
+    pub swarm: DistanceSection,
+    pub generous: GenerousSection,
+    pub suffocate: EmptySection,
+    pub mobility: EmptySection,
+
+    #[serde(rename = "same-color")]
+    pub same_color: ColorSection,
+
+    #[serde(rename = "opposite-color")]
+    pub opposite_color: ColorSection,
+}
+
+/// Backend settings which apply while this engine is chosen.
+///
+/// Only engines which use the backend have these.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct EngineSection {
+    pub nodes: Option<u64>,
+    pub evaluation_mode: Option<String>,
+}
+
+/// The section of an engine which has no settings, so any key in it is a mistake.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmptySection {}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ScovilleSection {
+    pub percent: Option<f32>,
+    pub nodes: Option<u64>,
+    pub evaluation_mode: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct AlphabeticalSection {
+    pub notation: Option<String>,
+    pub order: Option<String>,
+}
+
+/// Settings for engines which measure distances across the board.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DistanceSection {
+    pub metric: Option<String>,
+}
+
+/// Settings for engines which care about the color of the squares their pieces stand on.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ColorSection {
+    pub mode: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GenerousSection {
+    pub weights: Option<WeightsSection>,
+    pub mode: Option<String>,
+}
+
+/// Piece weights, where any which are left out keep their default.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeightsSection {
+    pub pawn: Option<u32>,
+    pub knight: Option<u32>,
+    pub bishop: Option<u32>,
+    pub rook: Option<u32>,
+    pub queen: Option<u32>,
+}
+
+impl WeightsSection {
+    pub fn apply(&self, weights: Weights) -> Weights {
+        Weights {
+            pawn: self.pawn.unwrap_or(weights.pawn),
+            knight: self.knight.unwrap_or(weights.knight),
+            bishop: self.bishop.unwrap_or(weights.bishop),
+            rook: self.rook.unwrap_or(weights.rook),
+            queen: self.queen.unwrap_or(weights.queen),
+        }
+    }
+}
+
+impl EngineSections {
+    /// The backend settings for the given engine, which are empty if it doesn't use the backend.
+    pub fn backend(&self, kind: EngineKind) -> EngineSection {
+        match kind {
+            EngineKind::Stockfish => self.stockfish.clone(),
+            EngineKind::Mediocrefish => self.mediocrefish.clone(),
+            EngineKind::Drawfish => self.drawfish.clone(),
+            EngineKind::Worstfish => self.worstfish.clone(),
+            EngineKind::Scoville => EngineSection {
+                nodes: self.scoville.nodes,
+                evaluation_mode: self.scoville.evaluation_mode.clone(),
+            },
+            _ => EngineSection::default(),
+        }
+    }
+}
+
+impl ConfigFile {
+    /// Reads the configuration file at `path`, or at the default location if there is one.
+    ///
+    /// The file is optional, unless its path was given explicitly.
+    pub fn load(path: Option<&Path>) -> Result<(Self, Option<PathBuf>)> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.is_file() => path,
+                _ => return Ok((ConfigFile::default(), None)),
+            },
+        };
+
+        let invalid = |reason: String| Error::InvalidConfigFile {
+            path: path.clone(),
+            reason,
+        };
+
+        let contents = fs::read_to_string(&path).map_err(|error| invalid(error.to_string()))?;
+        let file = ConfigFile::parse(&contents).map_err(invalid)?;
+        Ok((file, Some(path)))
+    }
+
+    fn parse(contents: &str) -> std::result::Result<Self, String> {
+        toml::from_str(contents).map_err(|error| error.to_string())
+    }
+}
+
+/// Where the configuration file lives if none is given, such as `~/.config/mallard-chess/config.toml`.
+fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")).join("config.toml"))
+}
+
+#[test]
+fn parse_sections() {
+    let file = ConfigFile::parse(
+        r#"
+        seed = 42
+
+        [backend]
+        path = "/usr/games/stockfish"
+        nodes = 10000
+        options = { Hash = 256, "Skill Level" = "5", Ponder = false }
+
+        [engine.scoville]
+        percent = 25
+        nodes = 500
+
+        [engine.worstfish]
+        evaluation-mode = "per-move"
+        "#,
+    )
+    .expect("Unable to parse config file");
+
+    assert_eq!(file.seed, Some(42));
+    assert_eq!(file.backend.nodes, Some(10000));
+    assert_eq!(file.backend.options["Hash"].to_uci(), "256");
+    assert_eq!(file.backend.options["Skill Level"].to_uci(), "5");
+    assert_eq!(file.backend.options["Ponder"].to_uci(), "false");
+    assert_eq!(file.engine.scoville.percent, Some(25.0));
+    assert_eq!(file.engine.backend(EngineKind::Scoville).nodes, Some(500));
+    assert_eq!(
+        file.engine
+            .backend(EngineKind::Worstfish)
+            .evaluation_mode
+            .as_deref(),
+        Some("per-move"),
+    );
+    assert_eq!(file.engine.backend(EngineKind::Random).nodes, None);
+}
+
+#[test]
+fn unknown_keys() {
+    let error = ConfigFile::parse("[backend]\nnode = 5\n").expect_err("Accepted unknown key");
+    assert!(error.contains("unknown field `node`"), "{}", error);
+
+    let error =
+        ConfigFile::parse("[engine.random]\npercent = 5\n").expect_err("Accepted unknown key");
+    assert!(error.contains("unknown field `percent`"), "{}", error);
+
+    // Engines which don't use the backend have no backend settings
+    let error =
+        ConfigFile::parse("[engine.huddle]\nnodes = 5\n").expect_err("Accepted unknown key");
+    assert!(error.contains("unknown field `nodes`"), "{}", error);
+
+    let error = ConfigFile::parse("[engine.randon]\n").expect_err("Accepted unknown engine");
+    assert!(error.contains("unknown field `randon`"), "{}", error);
+}
@@ -0,0 +1,877 @@
+/*
+ * config/mod.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+mod file;
+
+use self::file::ConfigFile;
+use crate::backend::{BackendCommand, BackendOption, EvaluationMode, Supervision};
+use crate::engine::{EngineKind, ListFormat, Notation, Weights};
+use crate::error::{Error, Result};
+use crate::game::{Protocol, Variant};
+use crate::squares::{ColorMode, Metric};
+use clap::{Arg, ArgMatches, Command};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use strum::IntoEnumIterator;
+
+#[cfg(target_os = "windows")]
+const DEFAULT_LOG_PATH: &str = "mallard-chess.log";
+
+#[cfg(not(target_os = "windows"))]
+const DEFAULT_LOG_PATH: &str = "/tmp/mallard-chess.log";
+
+/// What the program was asked to do on the command line.
+#[derive(Debug)]
+pub enum Invocation {
+    Play(Box<Configuration>),
+    ListEngines(ListFormat),
+}
+
+#[derive(Debug)]
+pub struct Configuration {
+    pub log_file: Arc<File>,
+    pub engine_kind: EngineKind,
+    pub backend: BackendCommand,
+    pub backend_options: Vec<BackendOption>,
+    pub supervision: Supervision,
+    pub backend_processes: usize,
+    pub cache_size: usize,
+    pub cache_file: Option<PathBuf>,
+    pub backend_nodes: Option<u64>,
+    pub evaluation_mode: EvaluationMode,
+
+    /// The node limit and evaluation mode of engines without their own.
+    pub global_backend_nodes: Option<u64>,
+    pub global_evaluation_mode: EvaluationMode,
+    pub engine_overrides: HashMap<EngineKind, EngineOverrides>,
+
+    pub scoville_percent: f32,
+    pub alphabetical_notation: Notation,
+    pub alphabetical_last: bool,
+    pub huddle_metric: Metric,
+    pub swarm_metric: Metric,
+    pub generous_weights: Weights,
+    pub generous_queen_first: bool,
+    pub same_color_mode: ColorMode,
+    pub opposite_color_mode: ColorMode,
+    pub seed: Option<u64>,
+    pub ponder: bool,
+    pub chess960: bool,
+    pub variant: Variant,
+    pub protocol: Option<Protocol>,
+}
+
+impl Configuration {
+    pub fn load() -> Result<Invocation> {
+        let matches = Command::new("Mallard Chess")
+            .author(env!("CARGO_PKG_AUTHORS"))
+            .version(env!("CARGO_PKG_VERSION"))
+            .about(env!("CARGO_PKG_DESCRIPTION"))
+            .arg(
+                Arg::new("config")
+                    .short('c')
+                    .long("config")
+                    .takes_value(true)
+                    .allow_invalid_utf8(true)
+                    .value_name("PATH")
+                    .help("Configuration file to read settings from, instead of the one in the user's config directory"),
+            )
+            .arg(
+                Arg::new("log-file")
+                    .short('L')
+                    .long("log")
+                    .long("log-file")
+                    .takes_value(true)
+                    .allow_invalid_utf8(true)
+                    .value_name("PATH")
+                    .default_value(DEFAULT_LOG_PATH)
+                    .help("Log file to output to"),
+            )
+            .arg(
+                Arg::new("scoville-percent")
+                    .short('P')
+                    .long("percent")
+                    .long("scoville-percent")
+                    .takes_value(true)
+                    .value_name("PERCENT")
+                    .default_value("50")
+                    .help(
+                        "What concentration percentage of Stockfish to use in the Scoville engine",
+                    ),
+            )
+            .arg(
+                Arg::new("alphabetical-notation")
+                    .long("alphabetical-notation")
+                    .takes_value(true)
+                    .value_name("NOTATION")
+                    .possible_values(["san", "uci"])
+                    .default_value("san")
+                    .help("Which notation the Alphabetical engine sorts moves by"),
+            )
+            .arg(
+                Arg::new("alphabetical-order")
+                    .long("alphabetical-order")
+                    .takes_value(true)
+                    .value_name("ORDER")
+                    .possible_values(["first", "last"])
+                    .default_value("first")
+                    .help("Whether the Alphabetical engine plays the first or last move in order"),
+            )
+            .arg(
+                Arg::new("huddle-metric")
+                    .long("huddle-metric")
+                    .takes_value(true)
+                    .value_name("METRIC")
+                    .possible_values(["chebyshev", "manhattan"])
+                    .default_value("chebyshev")
+                    .help("How the Huddle engine measures the distance to its king"),
+            )
+            .arg(
+                Arg::new("swarm-metric")
+                    .long("swarm-metric")
+                    .takes_value(true)
+                    .value_name("METRIC")
+                    .possible_values(["chebyshev", "manhattan"])
+                    .default_value("chebyshev")
+                    .help("How the Swarm engine measures the distance to the opponent's king"),
+            )
+            .arg(
+                Arg::new("generous-weights")
+                    .long("generous-weights")
+                    .takes_value(true)
+                    .value_name("P,N,B,R,Q")
+                    .default_value("1,3,3,5,9")
+                    .help("How much the Generous engine values giving away each kind of piece"),
+            )
+            .arg(
+                Arg::new("generous-mode")
+                    .long("generous-mode")
+                    .takes_value(true)
+                    .value_name("MODE")
+                    .possible_values(["material", "queen-first"])
+                    .default_value("material")
+                    .help("Whether the Generous engine hangs the most material, or its queen before anything else"),
+            )
+            .arg(
+                Arg::new("same-color-mode")
+                    .long("same-color-mode")
+                    .takes_value(true)
+                    .value_name("MODE")
+                    .possible_values(["king", "light", "dark"])
+                    .default_value("king")
+                    .help("Which squares the Same Color engine keeps its pieces on, where king means the color its king started on"),
+            )
+            .arg(
+                Arg::new("opposite-color-mode")
+                    .long("opposite-color-mode")
+                    .takes_value(true)
+                    .value_name("MODE")
+                    .possible_values(["king", "light", "dark"])
+                    .default_value("king")
+                    .help("Which squares the Opposite Color engine keeps its pieces off, where king means the color its king started on"),
+            )
+            .arg(
+                Arg::new("seed")
+                    .long("seed")
+                    .takes_value(true)
+                    .value_name("SEED")
+                    .help("Seed for the random number generator, to replay a game from its log"),
+            )
+            .arg(
+                Arg::new("backend")
+                    .short('B')
+                    .long("backend")
+                    .takes_value(true)
+                    .allow_invalid_utf8(true)
+                    .value_name("PATH")
+                    .default_value("stockfish")
+                    .help("Path to the UCI engine to use as the backend"),
+            )
+            .arg(
+                Arg::new("backend-arg")
+                    .long("backend-arg")
+                    .takes_value(true)
+                    .allow_invalid_utf8(true)
+                    .multiple_occurrences(true)
+                    .allow_hyphen_values(true)
+                    .value_name("ARG")
+                    .help("Command-line argument to pass to the backend engine (may be repeated)"),
+            )
+            .arg(
+                Arg::new("backend-dir")
+                    .long("backend-dir")
+                    .takes_value(true)
+                    .allow_invalid_utf8(true)
+                    .value_name("DIRECTORY")
+                    .help("Working directory to run the backend engine in"),
+            )
+            .arg(
+                Arg::new("backend-option")
+                    .short('O')
+                    .long("backend-option")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .value_name("NAME=VALUE")
+                    .help("UCI option to set on the backend engine, such as Hash=256 (may be repeated)"),
+            )
+            .arg(
+                Arg::new("backend-timeout")
+                    .long("backend-timeout")
+                    .takes_value(true)
+                    .value_name("SECONDS")
+                    .default_value("60")
+                    .help("How long to wait on a silent backend before restarting it (0 to wait forever)"),
+            )
+            .arg(
+                Arg::new("backend-retries")
+                    .long("backend-retries")
+                    .takes_value(true)
+                    .value_name("COUNT")
+                    .default_value("2")
+                    .help("How many times to restart the backend and retry a failed evaluation"),
+            )
+            .arg(
+                Arg::new("backend-processes")
+                    .short('j')
+                    .long("backend-processes")
+                    .takes_value(true)
+                    .value_name("COUNT")
+                    .default_value("1")
+                    .help("How many backend processes to spread per-move searches across"),
+            )
+            .arg(
+                Arg::new("cache-size")
+                    .short('C')
+                    .long("cache-size")
+                    .takes_value(true)
+                    .value_name("ENTRIES")
+                    .default_value("100000")
+                    .help("How many backend evaluations to keep in memory (0 to only use the cache file, if any)"),
+            )
+            .arg(
+                Arg::new("cache-file")
+                    .long("cache-file")
+                    .takes_value(true)
+                    .allow_invalid_utf8(true)
+                    .value_name("PATH")
+                    .help("File to persist backend evaluations in, shared across runs"),
+            )
+            .arg(
+                Arg::new("backend-nodes")
+                    .short('N')
+                    .long("nodes")
+                    .takes_value(true)
+                    .value_name("NODES")
+                    .default_value("unlimited")
+                    .help("Number of nodes for the backend engine to explore in its evaluation"),
+            )
+            .arg(
+                Arg::new("evaluation-mode")
+                    .short('E')
+                    .long("evaluation-mode")
+                    .takes_value(true)
+                    .value_name("MODE")
+                    .possible_values(["multipv", "per-move"])
+                    .default_value("multipv")
+                    .help(
+                        "Whether to score all legal moves with one MultiPV search, or one search per move",
+                    ),
+            )
+            .arg(
+                Arg::new("protocol")
+                    .long("protocol")
+                    .takes_value(true)
+                    .value_name("PROTOCOL")
+                    .possible_values(["auto", "uci", "xboard"])
+                    .default_value("auto")
+                    .help("Which protocol to speak with the GUI, or auto to detect it from the first command"),
+            )
+            .arg(
+                Arg::new("engine")
+                    .required(true)
+                    .takes_value(true)
+                    .value_name("NAME")
+                    .help("What internal engine to play using"),
+            )
+            .subcommand(
+                Command::new("list")
+                    .about("Lists every engine, with its aliases and description")
+                    .arg(
+                        Arg::new("format")
+                            .long("format")
+                            .takes_value(true)
+                            .value_name("FORMAT")
+                            .possible_values(["text", "json"])
+                            .default_value("text")
+                            .help("Whether to print the list for people or for scripts"),
+                    ),
+            )
+            .subcommand_negates_reqs(true)
+            .get_matches();
+
+        if let Some(matches) = matches.subcommand_matches("list") {
+            let value = matches
+                .value_of("format")
+                .expect("Missing default argument");
+
+            let format = match value {
+                "text" => ListFormat::Text,
+                "json" => ListFormat::Json,
+                _ => unreachable!("Invalid list format passed clap validation"),
+            };
+
+            return Ok(Invocation::ListEngines(format));
+        }
+
+        let (file, file_path) = ConfigFile::load(matches.value_of_os("config").map(Path::new))?;
+
+        let engine_kind = {
+            let value = matches
+                .value_of("engine")
+                .expect("Missing required argument");
+
+            EngineKind::try_from(value).map_err(|name| Error::UnknownEngine(name.into()))?
+        };
+
+        // Each engine's own settings take precedence over the global ones,
+        // but not over anything given on the command line
+        let mut engine_overrides = HashMap::new();
+        for kind in EngineKind::iter() {
+            let section = file.engine.backend(kind);
+            let mut overrides = EngineOverrides::default();
+
+            if matches.occurrences_of("backend-nodes") == 0 {
+                overrides.backend_nodes = section.nodes;
+            }
+
+            if matches.occurrences_of("evaluation-mode") == 0 {
+                if let Some(ref value) = section.evaluation_mode {
+                    overrides.evaluation_mode = Some(parse_evaluation_mode(value)?);
+                }
+            }
+
+            if overrides != EngineOverrides::default() {
+                engine_overrides.insert(kind, overrides);
+            }
+        }
+
+        let log_file = {
+            let path = path_setting(&matches, "log-file", file.log_file)
+                .expect("Missing default argument");
+
+            Arc::new(File::create(path)?)
+        };
+
+        if let Some(ref path) = file_path {
+            log!(log_file, "Loaded configuration file {}", path.display());
+        }
+
+        let scoville_percent = {
+            let value = setting(
+                &matches,
+                "scoville-percent",
+                file.engine
+                    .scoville
+                    .percent
+                    .map(|percent| percent.to_string()),
+            )
+            .expect("Missing default argument");
+
+            let percent: f32 = value
+                .parse()
+                .map_err(|error| invalid("Scoville engine percent", &value, error))?;
+
+            if !percent.is_normal() || !(0.0..=100.0).contains(&percent) {
+                return Err(invalid(
+                    "Scoville engine percent",
+                    &value,
+                    "not between 0-100%",
+                ));
+            }
+
+            percent
+        };
+
+        let alphabetical_notation = {
+            let value = setting(
+                &matches,
+                "alphabetical-notation",
+                file.engine.alphabetical.notation,
+            )
+            .expect("Missing default argument");
+
+            match value.as_str() {
+                "san" => Notation::San,
+                "uci" => Notation::Uci,
+                _ => {
+                    return Err(invalid(
+                        "Alphabetical engine notation",
+                        &value,
+                        "must be san or uci",
+                    ))
+                }
+            }
+        };
+
+        let alphabetical_last = {
+            let value = setting(
+                &matches,
+                "alphabetical-order",
+                file.engine.alphabetical.order,
+            )
+            .expect("Missing default argument");
+
+            match value.as_str() {
+                "first" => false,
+                "last" => true,
+                _ => {
+                    return Err(invalid(
+                        "Alphabetical engine order",
+                        &value,
+                        "must be first or last",
+                    ))
+                }
+            }
+        };
+
+        let huddle_metric = {
+            let value = setting(&matches, "huddle-metric", file.engine.huddle.metric)
+                .expect("Missing default argument");
+
+            Metric::from_name(&value).ok_or_else(|| {
+                invalid(
+                    "Huddle engine metric",
+                    &value,
+                    "must be chebyshev or manhattan",
+                )
+            })?
+        };
+
+        let swarm_metric = {
+            let value = setting(&matches, "swarm-metric", file.engine.swarm.metric)
+                .expect("Missing default argument");
+
+            Metric::from_name(&value).ok_or_else(|| {
+                invalid(
+                    "Swarm engine metric",
+                    &value,
+                    "must be chebyshev or manhattan",
+                )
+            })?
+        };
+
+        let generous_weights = {
+            let weights = file
+                .engine
+                .generous
+                .weights
+                .map(|weights| weights.apply(Weights::default()).to_string());
+            let value =
+                setting(&matches, "generous-weights", weights).expect("Missing default argument");
+
+            value
+                .parse()
+                .map_err(|error| invalid("Generous engine weights", &value, error))?
+        };
+
+        let generous_queen_first = {
+            let value = setting(&matches, "generous-mode", file.engine.generous.mode)
+                .expect("Missing default argument");
+
+            match value.as_str() {
+                "material" => false,
+                "queen-first" => true,
+                _ => {
+                    return Err(invalid(
+                        "Generous engine mode",
+                        &value,
+                        "must be material or queen-first",
+                    ))
+                }
+            }
+        };
+
+        let same_color_mode = {
+            let value = setting(&matches, "same-color-mode", file.engine.same_color.mode)
+                .expect("Missing default argument");
+
+            ColorMode::from_name(&value).ok_or_else(|| {
+                invalid(
+                    "Same Color engine mode",
+                    &value,
+                    "must be king, light or dark",
+                )
+            })?
+        };
+
+        let opposite_color_mode = {
+            let value = setting(
+                &matches,
+                "opposite-color-mode",
+                file.engine.opposite_color.mode,
+            )
+            .expect("Missing default argument");
+
+            ColorMode::from_name(&value).ok_or_else(|| {
+                invalid(
+                    "Opposite Color engine mode",
+                    &value,
+                    "must be king, light or dark",
+                )
+            })?
+        };
+
+        let seed = setting(&matches, "seed", file.seed.map(|seed| seed.to_string()))
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|error| invalid("random seed", &value, error))
+            })
+            .transpose()?;
+
+        let backend = {
+            let program = path_setting(&matches, "backend", file.backend.path)
+                .expect("Missing default argument");
+
+            let args = match matches.values_of_os("backend-arg") {
+                Some(values) => values.map(OsString::from).collect(),
+                None => file
+                    .backend
+                    .args
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(OsString::from)
+                    .collect(),
+            };
+
+            let working_dir = path_setting(&matches, "backend-dir", file.backend.dir);
+
+            BackendCommand {
+                program: program.into_os_string(),
+                args,
+                working_dir,
+
+                #[cfg(test)]
+                mock: None,
+            }
+        };
+
+        // Options from the command line are sent last, so they replace any from the file
+        let backend_options = {
+            let mut options = file
+                .backend
+                .options
+                .iter()
+                .map(|(name, value)| BackendOption {
+                    name: name.clone(),
+                    value: value.to_uci(),
+                })
+                .collect::<Vec<_>>();
+
+            for value in matches.values_of("backend-option").into_iter().flatten() {
+                let option = value
+                    .parse()
+                    .map_err(|error| invalid("backend option", value, error))?;
+
+                options.push(option);
+            }
+
+            options
+        };
+
+        let supervision = {
+            let timeout = {
+                let value = setting(
+                    &matches,
+                    "backend-timeout",
+                    file.backend.timeout.map(|seconds| seconds.to_string()),
+                )
+                .expect("Missing default argument");
+
+                let seconds: f32 = value
+                    .parse()
+                    .map_err(|error| invalid("backend timeout", &value, error))?;
+
+                if !seconds.is_finite() || seconds < 0.0 {
+                    return Err(invalid("backend timeout", &value, "not a valid duration"));
+                }
+
+                if seconds == 0.0 {
+                    None
+                } else {
+                    Some(Duration::from_secs_f32(seconds))
+                }
+            };
+
+            let max_retries = {
+                let value = setting(
+                    &matches,
+                    "backend-retries",
+                    file.backend.retries.map(|retries| retries.to_string()),
+                )
+                .expect("Missing default argument");
+
+                value
+                    .parse()
+                    .map_err(|error| invalid("backend retry count", &value, error))?
+            };
+
+            Supervision {
+                timeout,
+                max_retries,
+            }
+        };
+
+        let backend_processes = {
+            let value = setting(
+                &matches,
+                "backend-processes",
+                file.backend
+                    .processes
+                    .map(|processes| processes.to_string()),
+            )
+            .expect("Missing default argument");
+
+            let processes = value
+                .parse()
+                .map_err(|error| invalid("backend process count", &value, error))?;
+
+            if processes == 0 {
+                return Err(invalid(
+                    "backend process count",
+                    &value,
+                    "must be at least 1",
+                ));
+            }
+
+            processes
+        };
+
+        let cache_size = {
+            let value = setting(
+                &matches,
+                "cache-size",
+                file.cache.size.map(|size| size.to_string()),
+            )
+            .expect("Missing default argument");
+
+            value
+                .parse()
+                .map_err(|error| invalid("cache size", &value, error))?
+        };
+
+        let cache_file = path_setting(&matches, "cache-file", file.cache.file);
+
+        let backend_nodes = {
+            let value = setting(
+                &matches,
+                "backend-nodes",
+                file.backend.nodes.map(|nodes| nodes.to_string()),
+            )
+            .expect("Missing default argument");
+
+            if value == "-" || value == "unlimited" {
+                None
+            } else {
+                let nodes = value
+                    .parse()
+                    .map_err(|error| invalid("backend node depth", &value, error))?;
+
+                Some(nodes)
+            }
+        };
+
+        let evaluation_mode = {
+            let value = setting(&matches, "evaluation-mode", file.backend.evaluation_mode)
+                .expect("Missing default argument");
+
+            parse_evaluation_mode(&value)?
+        };
+
+        let protocol = {
+            let value =
+                setting(&matches, "protocol", file.protocol).expect("Missing default argument");
+
+            match value.as_str() {
+                "auto" => None,
+                "uci" => Some(Protocol::Uci),
+                "xboard" => Some(Protocol::Xboard),
+                _ => return Err(invalid("protocol", &value, "must be auto, uci or xboard")),
+            }
+        };
+
+        let mut config = Configuration {
+            log_file,
+            engine_kind,
+            backend,
+            backend_options,
+            supervision,
+            backend_processes,
+            cache_size,
+            cache_file,
+            backend_nodes,
+            evaluation_mode,
+            global_backend_nodes: backend_nodes,
+            global_evaluation_mode: evaluation_mode,
+            engine_overrides,
+            scoville_percent,
+            alphabetical_notation,
+            alphabetical_last,
+            huddle_metric,
+            swarm_metric,
+            generous_weights,
+            generous_queen_first,
+            same_color_mode,
+            opposite_color_mode,
+            seed,
+            ponder: false,
+            chess960: false,
+            variant: Variant::Standard,
+            protocol,
+        };
+
+        config.set_engine(engine_kind);
+        Ok(Invocation::Play(Box::new(config)))
+    }
+
+    /// Switches to another engine, along with its own backend settings.
+    pub fn set_engine(&mut self, engine_kind: EngineKind) {
+        let overrides = self
+            .engine_overrides
+            .get(&engine_kind)
+            .copied()
+            .unwrap_or_default();
+
+        self.engine_kind = engine_kind;
+        self.backend_nodes = overrides.backend_nodes.or(self.global_backend_nodes);
+        self.evaluation_mode = overrides
+            .evaluation_mode
+            .unwrap_or(self.global_evaluation_mode);
+    }
+
+    /// Sets the node limit for every engine, replacing their own settings.
+    pub fn set_backend_nodes(&mut self, nodes: Option<u64>) {
+        self.backend_nodes = nodes;
+        self.global_backend_nodes = nodes;
+
+        for overrides in self.engine_overrides.values_mut() {
+            overrides.backend_nodes = None;
+        }
+    }
+
+    /// Sets the evaluation mode for every engine, replacing their own settings.
+    pub fn set_evaluation_mode(&mut self, mode: EvaluationMode) {
+        self.evaluation_mode = mode;
+        self.global_evaluation_mode = mode;
+
+        for overrides in self.engine_overrides.values_mut() {
+            overrides.evaluation_mode = None;
+        }
+    }
+}
+
+/// Backend settings from an engine's section of the configuration file,
+/// which replace the global ones while that engine is chosen.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct EngineOverrides {
+    pub backend_nodes: Option<u64>,
+    pub evaluation_mode: Option<EvaluationMode>,
+}
+
+#[cfg(test)]
+impl Configuration {
+    /// Builds a default configuration which uses the in-process mock backend.
+    pub fn mock(engine_kind: EngineKind, script: crate::backend::mock::MockScript) -> Self {
+        let log_path =
+            std::env::temp_dir().join(format!("mallard-chess-test-{}.log", std::process::id()));
+
+        Configuration {
+            log_file: Arc::new(File::create(log_path).expect("Unable to create log file")),
+            engine_kind,
+            backend: BackendCommand {
+                mock: Some(script),
+                ..BackendCommand::default()
+            },
+            backend_options: Vec::new(),
+            supervision: Supervision {
+                timeout: Some(Duration::from_secs(10)),
+                max_retries: 0,
+            },
+            backend_processes: 1,
+            cache_size: 0,
+            cache_file: None,
+            backend_nodes: None,
+            evaluation_mode: EvaluationMode::MultiPv,
+            global_backend_nodes: None,
+            global_evaluation_mode: EvaluationMode::MultiPv,
+            engine_overrides: HashMap::new(),
+            scoville_percent: 50.0,
+            alphabetical_notation: Notation::San,
+            alphabetical_last: false,
+            huddle_metric: Metric::Chebyshev,
+            swarm_metric: Metric::Chebyshev,
+            generous_weights: Weights::default(),
+            generous_queen_first: false,
+            same_color_mode: ColorMode::King,
+            opposite_color_mode: ColorMode::King,
+            seed: Some(0),
+            ponder: false,
+            chess960: false,
+            variant: Variant::Standard,
+            protocol: Some(Protocol::Uci),
+        }
+    }
+}
+
+/// Reads a setting, preferring the command line, then the configuration file, then its default.
+fn setting(matches: &ArgMatches, name: &str, file: Option<String>) -> Option<String> {
+    match file {
+        Some(value) if matches.occurrences_of(name) == 0 => Some(value),
+        _ => matches.value_of(name).map(String::from),
+    }
+}
+
+/// Like `setting()`, but for paths, which may not be valid UTF-8.
+fn path_setting(matches: &ArgMatches, name: &str, file: Option<PathBuf>) -> Option<PathBuf> {
+    match file {
+        Some(path) if matches.occurrences_of(name) == 0 => Some(path),
+        _ => matches.value_of_os(name).map(PathBuf::from),
+    }
+}
+
+// Values from the file haven't been through clap's validation
+fn parse_evaluation_mode(value: &str) -> Result<EvaluationMode> {
+    match value {
+        "multipv" => Ok(EvaluationMode::MultiPv),
+        "per-move" => Ok(EvaluationMode::PerMove),
+        _ => Err(invalid(
+            "evaluation mode",
+            value,
+            "must be multipv or per-move",
+        )),
+    }
+}
+
+fn invalid<E: Display>(name: &'static str, value: &str, error: E) -> Error {
+    Error::InvalidArgument {
+        name,
+        value: value.into(),
+        reason: error.to_string(),
+    }
+}
@@ -0,0 +1,122 @@
+/*
+ * engine/alphabetical.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+use super::prelude::*;
+use crate::san::to_san;
+
+/// Which notation the Alphabetical engine writes moves in before sorting them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Notation {
+    /// Standard Algebraic Notation, as in PGN, such as `Nf3`.
+    San,
+
+    /// Coordinate notation, as sent over UCI, such as `g1f3`.
+    Uci,
+}
+
+impl Notation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Notation::San => "san",
+            Notation::Uci => "uci",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AlphabeticalEngine {
+    notation: Notation,
+    last: bool,
+}
+
+impl AlphabeticalEngine {
+    pub fn new(notation: Notation, last: bool) -> Self {
+        AlphabeticalEngine { notation, last }
+    }
+}
+
+impl Default for AlphabeticalEngine {
+    fn default() -> Self {
+        AlphabeticalEngine::new(Notation::San, false)
+    }
+}
+
+impl Engine for AlphabeticalEngine {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Alphabetical
+    }
+
+    fn name(&self) -> &'static str {
+        "Alphabetical"
+    }
+
+    fn description(&self) -> &'static str {
+        "Plays the move which comes first alphabetically, or optionally last"
+    }
+
+    fn needs_backend(&self) -> bool {
+        false
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
+        // Sorted by byte value, so capital letters (piece moves) come before pawn moves
+        let mut moves = game
+            .moves()
+            .map(|chess_move| {
+                let text = match self.notation {
+                    Notation::San => to_san(&game.board, chess_move),
+                    Notation::Uci => chess_move.to_string(),
+                };
+
+                (text, chess_move)
+            })
+            .collect::<Vec<_>>();
+
+        moves.sort_by(|(text1, _), (text2, _)| text1.cmp(text2));
+
+        log!(
+            game.log_file,
+            "Sorted possible moves alphabetically: {:?}",
+            moves,
+        );
+
+        let chosen = if self.last {
+            moves.last()
+        } else {
+            moves.first()
+        };
+
+        chosen
+            .map(|&(_, chess_move)| chess_move)
+            .ok_or(Error::NoLegalMoves)
+    }
+}
+
+#[test]
+fn chooses_first_move() {
+    use crate::backend::mock::MockScript;
+
+    const FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    let mut game = Game::mock(MockScript::new(), FEN);
+    let choose = |game: &mut Game, notation, last| {
+        AlphabeticalEngine::new(notation, last)
+            .choose_move(game)
+            .expect("Unable to choose move")
+            .to_string()
+    };
+
+    assert_eq!(choose(&mut game, Notation::San, false), "b1a3"); // Na3
+    assert_eq!(choose(&mut game, Notation::San, true), "h2h4"); // h4
+    assert_eq!(choose(&mut game, Notation::Uci, false), "a2a3");
+    assert_eq!(choose(&mut game, Notation::Uci, true), "h2h4");
+}
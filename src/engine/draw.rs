@@ -26,17 +26,32 @@ impl Engine for DrawfishEngine {
     }
 
     fn description(&self) -> &'static str {
-        "Attempts to force a stalemate"
+        "Attempts to force a stalemate or other draw"
     }
 
-    fn choose_move(&self, game: &mut Game) -> ChessMove {
+    fn needs_backend(&self) -> bool {
+        true
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
         let mut moves = game
-            .stockfish
-            .evaluate_possible_moves(&game.board)
+            .backend
+            .evaluate_possible_moves(&game.board)?
             .into_iter()
             .map(|ScoredMove { chess_move, score }| {
                 const BIG_VALUE: i32 = 1_000_000_000;
 
+                // Draws by repetition or the fifty-move rule are as good as a stalemate.
+                // Repeating a position is on the way to one, which takes at least four more halfmoves.
+                let history = game.history.after(&game.board, chess_move);
+                let score = if history.can_claim_draw() {
+                    Score::Stalemate(0)
+                } else if history.is_repetition() {
+                    Score::Stalemate(4)
+                } else {
+                    score
+                };
+
                 // If a stalemate is possible, then take it.
                 // If not, choose lowest-rated positive move.
                 // Checkmates of any kind are strongly disincentivized.
@@ -73,6 +88,54 @@ impl Engine for DrawfishEngine {
 
         // Choose the best-scoring move.
         // Because it sorts from least to greatest, this should be the last one.
-        moves.last().expect("No legal moves").0
+        moves
+            .last()
+            .map(|&(chess_move, _)| chess_move)
+            .ok_or(Error::NoLegalMoves)
+    }
+}
+
+#[test]
+fn chooses_stalemate() {
+    use crate::backend::mock::MockScript;
+
+    // Rb7 stalemates, Ra1 checkmates, and the backend likes neither
+    const FEN: &str = "k7/2K5/8/8/8/8/8/1R6 w - - 0 1";
+
+    let script = MockScript::new().position(
+        FEN,
+        &[
+            ("b1a1", 10_000),
+            ("b1b6", 900),
+            ("b1h1", 850),
+            ("c7c6", 800),
+        ],
+    );
+
+    let mut game = Game::mock(script, FEN);
+    let chess_move = DrawfishEngine
+        .choose_move(&mut game)
+        .expect("Unable to choose move");
+
+    assert_eq!(chess_move.to_string(), "b1b7");
+}
+
+#[test]
+fn chooses_threefold_repetition() {
+    use crate::backend::mock::MockScript;
+    use std::str::FromStr;
+
+    let mut game = Game::mock(
+        MockScript::new(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    );
+    for chess_move in &["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1"] {
+        game.play(ChessMove::from_str(chess_move).expect("Invalid move"));
     }
+
+    let chess_move = DrawfishEngine
+        .choose_move(&mut game)
+        .expect("Unable to choose move");
+
+    assert_eq!(chess_move.to_string(), "f6g8");
 }
@@ -0,0 +1,249 @@
+/*
+ * engine/generous.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+use super::choose_best;
+use super::prelude::*;
+use crate::chess960;
+use chess::{
+    get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_rook_moves, BitBoard,
+    Board, Color, Piece, EMPTY,
+};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// The largest weight a piece may be given, which keeps every score well within `QUEEN_FIRST`.
+const MAX_WEIGHT: u32 = 100;
+
+/// Added to the score for each queen left hanging, when those come first.
+const QUEEN_FIRST: i32 = 10_000;
+
+/// How much the Generous engine values giving away each kind of piece.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Weights {
+    pub pawn: u32,
+    pub knight: u32,
+    pub bishop: u32,
+    pub rook: u32,
+    pub queen: u32,
+}
+
+impl Weights {
+    fn get(self, piece: Piece) -> u32 {
+        match piece {
+            Piece::Pawn => self.pawn,
+            Piece::Knight => self.knight,
+            Piece::Bishop => self.bishop,
+            Piece::Rook => self.rook,
+            Piece::Queen => self.queen,
+            Piece::King => 0, // Can't be captured
+        }
+    }
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            pawn: 1,
+            knight: 3,
+            bishop: 3,
+            rook: 5,
+            queen: 9,
+        }
+    }
+}
+
+impl Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{},{}",
+            self.pawn, self.knight, self.bishop, self.rook, self.queen,
+        )
+    }
+}
+
+impl FromStr for Weights {
+    type Err = String;
+
+    /// Parses the weights of a pawn, knight, bishop, rook and queen, in that order, such as `1,3,3,5,9`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let weights = s
+            .split(',')
+            .map(|weight| {
+                let weight = weight
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|error| error.to_string())?;
+
+                if weight > MAX_WEIGHT {
+                    return Err(format!("weights may be at most {}", MAX_WEIGHT));
+                }
+
+                Ok(weight)
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        match weights[..] {
+            [pawn, knight, bishop, rook, queen] => Ok(Weights {
+                pawn,
+                knight,
+                bishop,
+                rook,
+                queen,
+            }),
+            _ => Err("expected pawn, knight, bishop, rook and queen weights".into()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GenerousEngine {
+    weights: Weights,
+    queen_first: bool,
+}
+
+impl GenerousEngine {
+    pub fn new(weights: Weights, queen_first: bool) -> Self {
+        GenerousEngine {
+            weights,
+            queen_first,
+        }
+    }
+
+    /// Adds up the weights of this player's pieces which the opponent could capture.
+    fn score(&self, board: &Board, color: Color) -> i32 {
+        let hanging = *board.color_combined(color) & attacked_squares(board, !color);
+
+        hanging
+            .map(|square| {
+                let piece = board.piece_on(square).expect("No piece on occupied square");
+                let mut score = self.weights.get(piece) as i32;
+
+                if self.queen_first && piece == Piece::Queen {
+                    score += QUEEN_FIRST;
+                }
+
+                score
+            })
+            .sum()
+    }
+}
+
+impl Engine for GenerousEngine {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Generous
+    }
+
+    fn name(&self) -> &'static str {
+        "Generous"
+    }
+
+    fn description(&self) -> &'static str {
+        "Leaves as much of its material as possible where the opponent can capture it"
+    }
+
+    fn needs_backend(&self) -> bool {
+        false
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
+        let color = game.board.side_to_move();
+        let moves = game
+            .moves()
+            .map(|chess_move| {
+                let possible_board = chess960::make_move(&game.board, chess_move);
+                (chess_move, self.score(&possible_board, color))
+            })
+            .collect::<Vec<_>>();
+
+        log!(
+            game.log_file,
+            "Scored possible moves for generosity: {:?}",
+            moves,
+        );
+
+        choose_best(moves, &mut game.rng)
+    }
+}
+
+/// Every square this player's pieces attack, ignoring pins.
+fn attacked_squares(board: &Board, color: Color) -> BitBoard {
+    let blockers = *board.combined();
+
+    board
+        .color_combined(color)
+        .map(|square| match board.piece_on(square) {
+            Some(Piece::Pawn) => get_pawn_attacks(square, color, !EMPTY),
+            Some(Piece::Knight) => get_knight_moves(square),
+            Some(Piece::Bishop) => get_bishop_moves(square, blockers),
+            Some(Piece::Rook) => get_rook_moves(square, blockers),
+            Some(Piece::Queen) => {
+                get_bishop_moves(square, blockers) | get_rook_moves(square, blockers)
+            }
+            Some(Piece::King) => get_king_moves(square),
+            None => EMPTY,
+        })
+        .fold(EMPTY, |attacks, piece_attacks| attacks | piece_attacks)
+}
+
+#[test]
+fn parse_weights() {
+    assert_eq!("1,3,3,5,9".parse(), Ok(Weights::default()));
+    assert_eq!(
+        " 0, 1,2 ,3,4".parse(),
+        Ok(Weights {
+            pawn: 0,
+            knight: 1,
+            bishop: 2,
+            rook: 3,
+            queen: 4,
+        }),
+    );
+    assert!("1,3,3,5".parse::<Weights>().is_err());
+    assert!("1,3,3,5,900".parse::<Weights>().is_err());
+    assert!("1,3,x,5,9".parse::<Weights>().is_err());
+}
+
+#[test]
+fn hangs_material() {
+    use crate::backend::mock::MockScript;
+
+    // The knight attacks e4 and g4, which the rook can reach, and d5, g4 and h5, which the queen can
+    const FEN: &str = "7k/8/5n2/8/R7/8/8/3Q3K w - - 0 1";
+
+    let cheap_queen = Weights {
+        queen: 1,
+        ..Weights::default()
+    };
+
+    let mut game = Game::mock(MockScript::new(), FEN);
+    let mut moved_piece = |engine: GenerousEngine| {
+        let chess_move = engine
+            .choose_move(&mut game)
+            .expect("Unable to choose move");
+
+        game.board.piece_on(chess_move.get_source())
+    };
+
+    assert_eq!(
+        moved_piece(GenerousEngine::new(Weights::default(), false)),
+        Some(Piece::Queen),
+    );
+    assert_eq!(
+        moved_piece(GenerousEngine::new(cheap_queen, false)),
+        Some(Piece::Rook),
+    );
+    assert_eq!(
+        moved_piece(GenerousEngine::new(cheap_queen, true)),
+        Some(Piece::Queen),
+    );
+}
@@ -0,0 +1,159 @@
+/*
+ * engine/greedy.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+use super::choose_best;
+use super::prelude::*;
+use crate::antichess::AntichessBoard;
+use chess::{Color, Piece};
+
+/// The score of a won antichess position, beyond any material difference.
+const WIN: i32 = 1000;
+
+#[derive(Debug)]
+pub struct GreedyEngine;
+
+impl Engine for GreedyEngine {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Greedy
+    }
+
+    fn name(&self) -> &'static str {
+        "Greedy"
+    }
+
+    fn description(&self) -> &'static str {
+        "Takes the most valuable piece it can. In antichess, gives away as much as it can instead."
+    }
+
+    fn needs_backend(&self) -> bool {
+        false
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
+        let board = &game.board;
+        let moves = game
+            .moves()
+            .map(|chess_move| {
+                // Capturing en passant is the only capture onto an empty square,
+                // and castling in Chess960 is written as taking our own rook
+                let captured = match board.piece_on(chess_move.get_dest()) {
+                    Some(piece)
+                        if board.color_on(chess_move.get_dest()) == Some(!board.side_to_move()) =>
+                    {
+                        piece_value(piece)
+                    }
+                    Some(_) => 0,
+                    None if board.piece_on(chess_move.get_source()) == Some(Piece::Pawn)
+                        && chess_move.get_source().get_file()
+                            != chess_move.get_dest().get_file() =>
+                    {
+                        piece_value(Piece::Pawn)
+                    }
+                    None => 0,
+                };
+
+                (chess_move, captured)
+            })
+            .collect::<Vec<_>>();
+
+        log!(
+            game.log_file,
+            "Scored possible moves for greed: {:?}",
+            moves
+        );
+        choose_best(moves, &mut game.rng)
+    }
+
+    fn choose_antichess_move(&self, game: &mut Game) -> Result<ChessMove> {
+        // Look at each of the opponent's replies, since captures are forced
+        // and the opponent may be made to take back what we gave away.
+
+        let board = &game.antichess;
+        let color = board.side_to_move();
+        let moves = board
+            .legal_moves()
+            .into_iter()
+            .map(|chess_move| {
+                let possible_board = board.make_move_new(chess_move);
+                let score = possible_board
+                    .legal_moves()
+                    .into_iter()
+                    .map(|reply| score_antichess(&possible_board.make_move_new(reply), color))
+                    .min()
+                    .unwrap_or(-WIN); // The opponent has no moves left, so they've won
+
+                (chess_move, score)
+            })
+            .collect::<Vec<_>>();
+
+        log!(
+            game.log_file,
+            "Scored possible antichess moves for greed: {:?}",
+            moves,
+        );
+
+        choose_best(moves, &mut game.rng)
+    }
+}
+
+/// Scores an antichess position for the given player, who wants as little material as possible.
+fn score_antichess(board: &AntichessBoard, color: Color) -> i32 {
+    match board.winner() {
+        Some(winner) if winner == color => WIN,
+        Some(_) => -WIN,
+        None => board.material(!color) as i32 - board.material(color) as i32,
+    }
+}
+
+fn piece_value(piece: Piece) -> i32 {
+    match piece {
+        Piece::Pawn => 1,
+        Piece::Knight | Piece::Bishop => 3,
+        Piece::Rook => 5,
+        Piece::Queen => 9,
+        Piece::King => 0,
+    }
+}
+
+#[test]
+fn takes_queen() {
+    use crate::backend::mock::MockScript;
+
+    // The rook can take either the queen or the knight
+    const FEN: &str = "4k3/8/8/3q4/8/8/8/K2R3n w - - 0 1";
+
+    let mut game = Game::mock(MockScript::new(), FEN);
+    let chess_move = GreedyEngine
+        .choose_move(&mut game)
+        .expect("Unable to choose move");
+
+    assert_eq!(chess_move.to_string(), "d1d5");
+}
+
+#[test]
+fn gives_away_material() {
+    use crate::backend::mock::MockScript;
+
+    // Moving the rook to c1 forces black to take it with the pawn,
+    // which leaves us without any pieces, so we win
+    const FEN: &str = "7k/8/8/8/8/8/1p6/R7 w - - 0 1";
+
+    let mut game = Game::mock(MockScript::new(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    game.config.variant = Variant::Antichess;
+    game.antichess = AntichessBoard::from_fen(FEN).expect("Invalid FEN");
+
+    let chess_move = GreedyEngine
+        .choose_antichess_move(&mut game)
+        .expect("Unable to choose move");
+
+    assert_eq!(chess_move.to_string(), "a1c1");
+}
@@ -0,0 +1,89 @@
+/*
+ * engine/huddle.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+use super::choose_best;
+use super::prelude::*;
+use crate::chess960;
+use crate::squares::{total_distance, Metric};
+
+#[derive(Debug)]
+pub struct HuddleEngine(Metric);
+
+impl HuddleEngine {
+    pub fn new(metric: Metric) -> Self {
+        HuddleEngine(metric)
+    }
+}
+
+impl Default for HuddleEngine {
+    fn default() -> Self {
+        HuddleEngine::new(Metric::Chebyshev)
+    }
+}
+
+impl Engine for HuddleEngine {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Huddle
+    }
+
+    fn name(&self) -> &'static str {
+        "Huddle"
+    }
+
+    fn description(&self) -> &'static str {
+        "Keeps its pieces as close to its own king as it can"
+    }
+
+    fn needs_backend(&self) -> bool {
+        false
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
+        let color = game.board.side_to_move();
+        let moves = game
+            .moves()
+            .map(|chess_move| {
+                // The king may be the piece moving, so find it after the move
+                let possible_board = chess960::make_move(&game.board, chess_move);
+                let king = possible_board.king_square(color);
+                let distance = total_distance(&possible_board, color, king, self.0);
+
+                (chess_move, -(distance as i32))
+            })
+            .collect::<Vec<_>>();
+
+        log!(
+            game.log_file,
+            "Scored possible moves for huddling: {:?}",
+            moves,
+        );
+
+        choose_best(moves, &mut game.rng)
+    }
+}
+
+#[test]
+fn moves_towards_king() {
+    use crate::backend::mock::MockScript;
+
+    // Bringing the far rook next to the king beats castling either way
+    const FEN: &str = "4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1";
+
+    let mut game = Game::mock(MockScript::new(), FEN);
+    for metric in [Metric::Chebyshev, Metric::Manhattan] {
+        let chess_move = HuddleEngine::new(metric)
+            .choose_move(&mut game)
+            .expect("Unable to choose move");
+
+        assert_eq!(chess_move.to_string(), "a1d1");
+    }
+}
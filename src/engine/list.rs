@@ -0,0 +1,90 @@
+/*
+ * engine/list.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! The `list` subcommand, which describes every available engine.
+
+use super::EngineKind;
+use crate::error::Result;
+use serde::Serialize;
+use std::io::{self, Write};
+use strum::IntoEnumIterator;
+
+/// How to print the list of engines.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ListFormat {
+    Text,
+    Json,
+}
+
+#[derive(Serialize, Debug)]
+struct EngineInfo {
+    name: &'static str,
+    description: &'static str,
+    aliases: Vec<&'static str>,
+    needs_backend: bool,
+}
+
+impl EngineInfo {
+    fn new(kind: EngineKind) -> Self {
+        let engine = kind.build_default();
+
+        EngineInfo {
+            name: engine.name(),
+            description: engine.description(),
+            aliases: kind.aliases(),
+            needs_backend: engine.needs_backend(),
+        }
+    }
+}
+
+/// Prints every engine to standard output.
+pub fn print_engines(format: ListFormat) -> Result<()> {
+    let engines = EngineKind::iter().map(EngineInfo::new).collect::<Vec<_>>();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+
+    match format {
+        ListFormat::Text => {
+            for info in engines {
+                writeln!(output, "{}", info.name)?;
+                writeln!(output, "    {}", info.description)?;
+                writeln!(output, "    Aliases: {}", info.aliases.join(", "))?;
+                writeln!(
+                    output,
+                    "    Backend: {}",
+                    if info.needs_backend { "yes" } else { "no" },
+                )?;
+                writeln!(output)?;
+            }
+        }
+        ListFormat::Json => {
+            serde_json::to_writer_pretty(&mut output, &engines).map_err(io::Error::from)?;
+            writeln!(output)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn aliases_round_trip() {
+    use std::convert::TryFrom;
+
+    for kind in EngineKind::iter() {
+        let info = EngineInfo::new(kind);
+        assert!(!info.aliases.is_empty(), "{:?} has no aliases", kind);
+
+        for alias in info.aliases {
+            assert_eq!(EngineKind::try_from(alias), Ok(kind));
+        }
+    }
+}
@@ -28,9 +28,43 @@ impl Engine for MediocrefishEngine {
         "Chooses the median of moves as ordered by score"
     }
 
-    fn choose_move(&self, game: &mut Game) -> ChessMove {
+    fn needs_backend(&self) -> bool {
+        true
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
         // Choose the move in the middle, sorted by score.
-        let moves = game.stockfish.evaluate_possible_moves(&game.board);
-        moves[moves.len() / 2].chess_move
+        let moves = game.backend.evaluate_possible_moves(&game.board)?;
+        moves
+            .get(moves.len() / 2)
+            .map(|scored_move| scored_move.chess_move)
+            .ok_or(Error::NoLegalMoves)
     }
 }
+
+#[test]
+fn chooses_median_move() {
+    use crate::backend::mock::MockScript;
+
+    const FEN: &str = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+
+    let script = MockScript::new().position(
+        FEN,
+        &[
+            ("e2e4", 50),
+            ("e2e3", 30),
+            ("e1d2", 10),
+            ("e1f2", 0),
+            ("e1d1", -20),
+            ("e1f1", -40),
+        ],
+    );
+
+    let mut game = Game::mock(script, FEN);
+    let chess_move = MediocrefishEngine
+        .choose_move(&mut game)
+        .expect("Unable to choose move");
+
+    // Sorted, the moves are f1 d1 f2 d2 e3 e4
+    assert_eq!(chess_move.to_string(), "e1d2");
+}
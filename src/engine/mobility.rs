@@ -0,0 +1,100 @@
+/*
+ * engine/mobility.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+use super::choose_best;
+use super::prelude::*;
+use crate::chess960;
+use chess::{Board, BoardStatus, MoveGen};
+
+/// The score of a checkmate, which beats any number of moves.
+const WIN: i32 = 1000;
+
+#[derive(Debug)]
+pub struct MobilityEngine;
+
+impl Engine for MobilityEngine {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Mobility
+    }
+
+    fn name(&self) -> &'static str {
+        "Mobility"
+    }
+
+    fn description(&self) -> &'static str {
+        "Gives itself as many legal moves as it can for its next turn"
+    }
+
+    fn needs_backend(&self) -> bool {
+        false
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
+        let moves = game
+            .moves()
+            .map(|chess_move| {
+                let possible_board = chess960::make_move(&game.board, chess_move);
+
+                let score = match possible_board.status() {
+                    BoardStatus::Ongoing => mobility(&possible_board),
+                    BoardStatus::Checkmate => WIN,
+                    BoardStatus::Stalemate => 0, // There is no next turn
+                };
+
+                (chess_move, score)
+            })
+            .collect::<Vec<_>>();
+
+        log!(
+            game.log_file,
+            "Scored possible moves for mobility: {:?}",
+            moves,
+        );
+
+        choose_best(moves, &mut game.rng)
+    }
+}
+
+/// Counts the legal moves the player who just moved would have if it were their turn again.
+///
+/// When they've given check, the opponent can't pass, so this instead
+/// counts the moves they have after the opponent's most restricting reply.
+fn mobility(board: &Board) -> i32 {
+    let count = |board: &Board| MoveGen::new_legal(board).len() as i32;
+
+    match board.null_move() {
+        Some(passed_board) => count(&passed_board),
+        None => MoveGen::new_legal(board)
+            .map(|reply| count(&board.make_move_new(reply)))
+            .min()
+            .unwrap_or(0),
+    }
+}
+
+#[test]
+fn frees_pieces() {
+    use crate::backend::mock::MockScript;
+
+    // Only pushing the b-pawn lets the bishop out of the corner
+    const FEN: &str = "4k3/8/8/8/8/8/1P6/B3K3 w - - 0 1";
+
+    let mut game = Game::mock(MockScript::new(), FEN);
+    let chess_move = MobilityEngine
+        .choose_move(&mut game)
+        .expect("Unable to choose move");
+
+    assert!(
+        ["b2b3", "b2b4"].contains(&chess_move.to_string().as_str()),
+        "Chose {}",
+        chess_move,
+    );
+}
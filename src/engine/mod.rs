@@ -12,29 +12,50 @@
 
 mod prelude {
     pub use super::{Engine, EngineKind};
-    pub use crate::game::Game;
+    pub use crate::error::{Error, Result};
+    pub use crate::game::{Game, Variant};
     pub use chess::ChessMove;
     pub use std::io::Write;
 }
 
+mod alphabetical;
 mod draw;
+mod generous;
+mod greedy;
+mod huddle;
+mod list;
 mod mediocre;
+mod mobility;
 mod pacifist;
 mod random;
 mod scoville;
+mod square_color;
 mod stockfish;
+mod suffocate;
+mod swarm;
 mod worstfish;
 
+pub use self::alphabetical::{AlphabeticalEngine, Notation};
 pub use self::draw::DrawfishEngine;
+pub use self::generous::{GenerousEngine, Weights};
+pub use self::greedy::GreedyEngine;
+pub use self::huddle::HuddleEngine;
+pub use self::list::{print_engines, ListFormat};
 pub use self::mediocre::MediocrefishEngine;
+pub use self::mobility::MobilityEngine;
 pub use self::pacifist::PacifistEngine;
 pub use self::random::RandomEngine;
 pub use self::scoville::ScovilleEngine;
+pub use self::square_color::SquareColorEngine;
 pub use self::stockfish::StockfishEngine;
+pub use self::suffocate::SuffocateEngine;
+pub use self::swarm::SwarmEngine;
 pub use self::worstfish::WorstfishEngine;
 
 use self::prelude::*;
 use crate::config::Configuration;
+use crate::squares::ColorMode;
+use rand::prelude::*;
 use std::convert::TryFrom;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
@@ -49,11 +70,40 @@ pub trait Engine {
     /// Returns a short constant string describing the behavior of the engine.
     fn description(&self) -> &'static str;
 
+    /// Whether the engine asks the backend for evaluations, rather than only looking at the board itself.
+    fn needs_backend(&self) -> bool;
+
     /// Given this game, determine what move the engine would like to play.
-    fn choose_move(&self, game: &mut Game) -> ChessMove;
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove>;
+
+    /// Like `choose_move()`, but also suggests which reply to ponder on.
+    fn choose_move_with_ponder(&self, game: &mut Game) -> Result<(ChessMove, Option<ChessMove>)> {
+        self.choose_move(game).map(|chess_move| (chess_move, None))
+    }
+
+    /// Like `choose_move()`, but for a game of antichess, played on `game.antichess`.
+    ///
+    /// Most engines only know regular chess, so by default this fails.
+    fn choose_antichess_move(&self, _game: &mut Game) -> Result<ChessMove> {
+        Err(Error::UnsupportedVariant(Variant::Antichess))
+    }
 }
 
-#[derive(EnumIter, Debug, Copy, Clone, PartialEq, Eq)]
+/// Chooses randomly between the moves with the highest score.
+///
+/// Engines which score each move share this, so ties are broken by the game's seeded generator.
+fn choose_best(moves: Vec<(ChessMove, i32)>, rng: &mut StdRng) -> Result<ChessMove> {
+    let max_score = moves.iter().map(|&(_, score)| score).max();
+    let best_moves = moves
+        .iter()
+        .filter(|&&(_, score)| Some(score) == max_score)
+        .map(|&(chess_move, _)| chess_move)
+        .collect::<Vec<_>>();
+
+    best_moves.choose(rng).copied().ok_or(Error::NoLegalMoves)
+}
+
+#[derive(EnumIter, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum EngineKind {
     Random,
     Pacifist,
@@ -62,6 +112,15 @@ pub enum EngineKind {
     Drawfish,
     Worstfish,
     Scoville,
+    Greedy,
+    Alphabetical,
+    Huddle,
+    Swarm,
+    Generous,
+    Suffocate,
+    Mobility,
+    SameColor,
+    OppositeColor,
 }
 
 impl EngineKind {
@@ -69,11 +128,44 @@ impl EngineKind {
         eprintln!("Possible values:");
 
         for variant in EngineKind::iter() {
-            eprintln!("- {:?}", variant);
+            eprintln!("- {}", variant.aliases().join(", "));
         }
+
+        eprintln!("Run with `list` for a description of each engine.");
+    }
+
+    /// All the names this engine can be chosen by, from the `TryFrom<&str>` table.
+    pub fn aliases(self) -> Vec<&'static str> {
+        ALIASES
+            .iter()
+            .filter(|&&(_, kind)| kind == self)
+            .map(|&(name, _)| name)
+            .collect()
     }
 
     pub fn build(self, config: &Configuration) -> Box<dyn Engine> {
+        match self {
+            EngineKind::Scoville => Box::new(ScovilleEngine::new(config.scoville_percent)),
+            EngineKind::Alphabetical => Box::new(AlphabeticalEngine::new(
+                config.alphabetical_notation,
+                config.alphabetical_last,
+            )),
+            EngineKind::Huddle => Box::new(HuddleEngine::new(config.huddle_metric)),
+            EngineKind::Swarm => Box::new(SwarmEngine::new(config.swarm_metric)),
+            EngineKind::Generous => Box::new(GenerousEngine::new(
+                config.generous_weights,
+                config.generous_queen_first,
+            )),
+            EngineKind::SameColor => Box::new(SquareColorEngine::same(config.same_color_mode)),
+            EngineKind::OppositeColor => {
+                Box::new(SquareColorEngine::opposite(config.opposite_color_mode))
+            }
+            _ => self.build_default(),
+        }
+    }
+
+    /// Builds the engine without any configuration, using its default settings.
+    pub fn build_default(self) -> Box<dyn Engine> {
         match self {
             EngineKind::Random => Box::new(RandomEngine),
             EngineKind::Pacifist => Box::new(PacifistEngine),
@@ -81,7 +173,16 @@ impl EngineKind {
             EngineKind::Mediocrefish => Box::new(MediocrefishEngine),
             EngineKind::Drawfish => Box::new(DrawfishEngine),
             EngineKind::Worstfish => Box::new(WorstfishEngine),
-            EngineKind::Scoville => Box::new(ScovilleEngine::new(config.scoville_percent)),
+            EngineKind::Scoville => Box::new(ScovilleEngine::default()),
+            EngineKind::Greedy => Box::new(GreedyEngine),
+            EngineKind::Alphabetical => Box::new(AlphabeticalEngine::default()),
+            EngineKind::Huddle => Box::new(HuddleEngine::default()),
+            EngineKind::Swarm => Box::new(SwarmEngine::default()),
+            EngineKind::Generous => Box::new(GenerousEngine::default()),
+            EngineKind::Suffocate => Box::new(SuffocateEngine),
+            EngineKind::Mobility => Box::new(MobilityEngine),
+            EngineKind::SameColor => Box::new(SquareColorEngine::same(ColorMode::King)),
+            EngineKind::OppositeColor => Box::new(SquareColorEngine::opposite(ColorMode::King)),
         }
     }
 }
@@ -89,31 +190,8 @@ impl EngineKind {
 impl<'a> TryFrom<&'a str> for EngineKind {
     type Error = &'a str;
 
-    fn try_from(name: &'a str) -> Result<EngineKind, &'a str> {
-        const VALUES: [(&str, EngineKind); 20] = [
-            ("rand", EngineKind::Random),
-            ("random", EngineKind::Random),
-            ("pacifist", EngineKind::Pacifist),
-            ("pacifism", EngineKind::Pacifist),
-            ("hippie", EngineKind::Pacifist),
-            ("boring", EngineKind::Stockfish),
-            ("dummy", EngineKind::Stockfish),
-            ("passthrough", EngineKind::Stockfish),
-            ("stockfish", EngineKind::Stockfish),
-            ("meh", EngineKind::Mediocrefish),
-            ("mediocre", EngineKind::Mediocrefish),
-            ("mediocrefish", EngineKind::Mediocrefish),
-            ("draw", EngineKind::Drawfish),
-            ("drawfish", EngineKind::Drawfish),
-            ("stale", EngineKind::Drawfish),
-            ("stalemate", EngineKind::Drawfish),
-            ("worst", EngineKind::Worstfish),
-            ("worstfish", EngineKind::Worstfish),
-            ("scoville", EngineKind::Scoville),
-            ("mix", EngineKind::Scoville),
-        ];
-
-        for (value, mode) in VALUES {
+    fn try_from(name: &'a str) -> std::result::Result<EngineKind, &'a str> {
+        for (value, mode) in ALIASES {
             if name.eq_ignore_ascii_case(value) {
                 return Ok(mode);
             }
@@ -122,3 +200,51 @@ impl<'a> TryFrom<&'a str> for EngineKind {
         Err(name)
     }
 }
+
+/// Every name an engine can be chosen by, on the command line or through the `Engine` option.
+const ALIASES: [(&str, EngineKind); 44] = [
+    ("rand", EngineKind::Random),
+    ("random", EngineKind::Random),
+    ("pacifist", EngineKind::Pacifist),
+    ("pacifism", EngineKind::Pacifist),
+    ("hippie", EngineKind::Pacifist),
+    ("boring", EngineKind::Stockfish),
+    ("dummy", EngineKind::Stockfish),
+    ("passthrough", EngineKind::Stockfish),
+    ("stockfish", EngineKind::Stockfish),
+    ("meh", EngineKind::Mediocrefish),
+    ("mediocre", EngineKind::Mediocrefish),
+    ("mediocrefish", EngineKind::Mediocrefish),
+    ("draw", EngineKind::Drawfish),
+    ("drawfish", EngineKind::Drawfish),
+    ("stale", EngineKind::Drawfish),
+    ("stalemate", EngineKind::Drawfish),
+    ("worst", EngineKind::Worstfish),
+    ("worstfish", EngineKind::Worstfish),
+    ("scoville", EngineKind::Scoville),
+    ("mix", EngineKind::Scoville),
+    ("greedy", EngineKind::Greedy),
+    ("glutton", EngineKind::Greedy),
+    ("alphabetical", EngineKind::Alphabetical),
+    ("alpha", EngineKind::Alphabetical),
+    ("abc", EngineKind::Alphabetical),
+    ("huddle", EngineKind::Huddle),
+    ("turtle", EngineKind::Huddle),
+    ("swarm", EngineKind::Swarm),
+    ("crowd", EngineKind::Swarm),
+    ("generous", EngineKind::Generous),
+    ("generosity", EngineKind::Generous),
+    ("gift", EngineKind::Generous),
+    ("suffocate", EngineKind::Suffocate),
+    ("min-oppt-moves", EngineKind::Suffocate),
+    ("min-opponent-moves", EngineKind::Suffocate),
+    ("mobility", EngineKind::Mobility),
+    ("max-mobility", EngineKind::Mobility),
+    ("max-own-moves", EngineKind::Mobility),
+    ("same-color", EngineKind::SameColor),
+    ("samecolor", EngineKind::SameColor),
+    ("same", EngineKind::SameColor),
+    ("opposite-color", EngineKind::OppositeColor),
+    ("oppositecolor", EngineKind::OppositeColor),
+    ("opposite", EngineKind::OppositeColor),
+];
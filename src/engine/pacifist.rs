@@ -10,10 +10,10 @@
  * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
  */
 
+use super::choose_best;
 use super::prelude::*;
-use chess::{Board, BoardStatus, MoveGen};
-use rand::prelude::*;
-use std::cmp;
+use crate::chess960;
+use chess::{Board, BoardStatus};
 
 #[derive(Debug)]
 pub struct PacifistEngine;
@@ -31,7 +31,11 @@ impl Engine for PacifistEngine {
         "Simple algorithm that avoids checkmate, check, and capture."
     }
 
-    fn choose_move(&self, game: &mut Game) -> ChessMove {
+    fn needs_backend(&self) -> bool {
+        false
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
         // Gather a list of all moves, with scores reflecting our priorities.
         //
         // From worst to best:
@@ -39,15 +43,19 @@ impl Engine for PacifistEngine {
         // * Capture
         // * Check
         // * Anything else
+        // * Repeating a position
+        // * Stalemate or a claimable draw
 
         // Gather the list of all moves
-        let mut possible_board = Board::default();
-
-        let mut moves = MoveGen::new_legal(&game.board)
+        let mut moves = game
+            .moves()
             .map(|chess_move| {
-                game.board.make_move(chess_move, &mut possible_board);
+                let possible_board = chess960::make_move(&game.board, chess_move);
 
+                let history = game.history.after(&game.board, chess_move);
                 let score = match possible_board.status() {
+                    BoardStatus::Ongoing if history.can_claim_draw() => 10, // Draws are as good as stalemates
+                    BoardStatus::Ongoing if history.is_repetition() => 2, // Heads towards a draw by repetition
                     BoardStatus::Ongoing => score_move(&game.board, &possible_board),
                     BoardStatus::Checkmate => -10, // Doesn't like checkmates of any kind
                     BoardStatus::Stalemate => 10, // Prefers stalemates because nobody is "conquering" the other
@@ -66,17 +74,50 @@ impl Engine for PacifistEngine {
             moves,
         );
 
-        // Find the highest possible score for this position
-        let max_score = moves
-            .iter()
-            .fold(-999, |best, &(_, score)| cmp::max(best, score));
+        choose_best(moves, &mut game.rng)
+    }
+
+    fn choose_antichess_move(&self, game: &mut Game) -> Result<ChessMove> {
+        // Captures are compulsory in antichess, so all we can do is avoid
+        // them when there's a choice, and not force the opponent into one.
+        //
+        // From worst to best:
+        // * Capture
+        // * Leaving the opponent a capture
+        // * Anything else
+
+        let board = &game.antichess;
+        let mut moves = board
+            .legal_moves()
+            .into_iter()
+            .map(|chess_move| {
+                let possible_board = board.make_move_new(chess_move);
+                let gives_capture = possible_board
+                    .legal_moves()
+                    .iter()
+                    .any(|&reply| possible_board.is_capture(reply));
+
+                let score = if board.is_capture(chess_move) {
+                    -5
+                } else if gives_capture {
+                    -1
+                } else {
+                    0
+                };
+
+                (chess_move, score)
+            })
+            .collect::<Vec<_>>();
 
-        // Then filter out all scores worse than that
-        moves.retain(|&(_, score)| score == max_score);
+        moves.sort_by_key(|&(_, score)| score);
+
+        log!(
+            game.log_file,
+            "Scored possible antichess moves for pacifism: {:?}",
+            moves,
+        );
 
-        // Choose a random move from our remaining selection
-        let mut rng = thread_rng();
-        moves.choose(&mut rng).expect("No legal moves").0
+        choose_best(moves, &mut game.rng)
     }
 }
 
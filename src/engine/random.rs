@@ -29,13 +29,45 @@ impl Engine for RandomEngine {
         "Chooses a random valid move"
     }
 
-    fn choose_move(&self, game: &mut Game) -> ChessMove {
-        let mut rng = thread_rng();
+    fn needs_backend(&self) -> bool {
+        false
+    }
 
-        *game
-            .moves()
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
+        game.moves()
             .collect::<Vec<_>>()
-            .choose(&mut rng)
-            .expect("No legal moves")
+            .choose(&mut game.rng)
+            .copied()
+            .ok_or(Error::NoLegalMoves)
+    }
+
+    fn choose_antichess_move(&self, game: &mut Game) -> Result<ChessMove> {
+        game.antichess
+            .legal_moves()
+            .choose(&mut game.rng)
+            .copied()
+            .ok_or(Error::NoLegalMoves)
     }
 }
+
+#[test]
+fn same_seed_same_moves() {
+    use crate::backend::mock::MockScript;
+
+    const FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    let choose_moves = || {
+        let mut game = Game::mock(MockScript::new(), FEN);
+        game.reset();
+
+        (0..10)
+            .map(|_| {
+                RandomEngine
+                    .choose_move(&mut game)
+                    .expect("Unable to choose move")
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(choose_moves(), choose_moves());
+}
@@ -32,6 +32,12 @@ impl ScovilleEngine {
     }
 }
 
+impl Default for ScovilleEngine {
+    fn default() -> Self {
+        ScovilleEngine::new(50.0)
+    }
+}
+
 impl Engine for ScovilleEngine {
     fn kind(&self) -> EngineKind {
         EngineKind::Scoville
@@ -45,9 +51,12 @@ impl Engine for ScovilleEngine {
         "Plays Stockfish X% of the time, with remaining turns diluted with random moves"
     }
 
-    fn choose_move(&self, game: &mut Game) -> ChessMove {
-        let mut rng = thread_rng();
-        if rng.gen::<f32>() < self.0 {
+    fn needs_backend(&self) -> bool {
+        true
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
+        if game.rng.gen::<f32>() < self.0 {
             log!(game.log_file, "Playing strong move");
             StockfishEngine.choose_move(game)
         } else {
@@ -56,3 +65,21 @@ impl Engine for ScovilleEngine {
         }
     }
 }
+
+#[test]
+fn full_strength_plays_best_move() {
+    use crate::backend::mock::MockScript;
+
+    const FEN: &str = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+
+    let script = MockScript::new().position(FEN, &[("e1d2", 40), ("e2e4", 20)]);
+    let mut game = Game::mock(script, FEN);
+    let engine = ScovilleEngine::new(100.0);
+
+    for _ in 0..10 {
+        let chess_move = engine
+            .choose_move(&mut game)
+            .expect("Unable to choose move");
+        assert_eq!(chess_move.to_string(), "e1d2");
+    }
+}
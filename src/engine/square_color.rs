@@ -0,0 +1,143 @@
+/*
+ * engine/square_color.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+use super::choose_best;
+use super::prelude::*;
+use crate::chess960;
+use crate::squares::{pieces_on, ColorMode};
+
+/// The Same Color and Opposite Color engines, which only differ in which squares they want.
+#[derive(Debug)]
+pub struct SquareColorEngine {
+    mode: ColorMode,
+
+    /// Whether to keep pieces off the chosen color, rather than on it.
+    invert: bool,
+}
+
+impl SquareColorEngine {
+    pub fn same(mode: ColorMode) -> Self {
+        SquareColorEngine {
+            mode,
+            invert: false,
+        }
+    }
+
+    pub fn opposite(mode: ColorMode) -> Self {
+        SquareColorEngine { mode, invert: true }
+    }
+}
+
+impl Engine for SquareColorEngine {
+    fn kind(&self) -> EngineKind {
+        if self.invert {
+            EngineKind::OppositeColor
+        } else {
+            EngineKind::SameColor
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        if self.invert {
+            "Opposite Color"
+        } else {
+            "Same Color"
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        if self.invert {
+            "Keeps as many of its pieces as it can off squares of one color, by default its king's"
+        } else {
+            "Keeps as many of its pieces as it can on squares of one color, by default its king's"
+        }
+    }
+
+    fn needs_backend(&self) -> bool {
+        false
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
+        let color = game.board.side_to_move();
+        let mut square_color = self.mode.square_color(&game.start, color);
+        if self.invert {
+            square_color = !square_color;
+        }
+
+        let moves = game
+            .moves()
+            .map(|chess_move| {
+                let possible_board = chess960::make_move(&game.board, chess_move);
+                let count = pieces_on(&possible_board, color, square_color);
+
+                (chess_move, count as i32)
+            })
+            .collect::<Vec<_>>();
+
+        log!(
+            game.log_file,
+            "Scored possible moves for {:?} squares: {:?}",
+            square_color,
+            moves,
+        );
+
+        choose_best(moves, &mut game.rng)
+    }
+}
+
+#[test]
+fn stays_on_color() {
+    use crate::backend::mock::MockScript;
+    use crate::squares::SquareColor;
+    use chess::Piece;
+
+    // The king starts on a dark square and the knight on a light one, and moving the knight always changes its color
+    const FEN: &str = "4k3/8/8/8/8/8/8/1N2K3 w - - 0 1";
+
+    let mut game = Game::mock(MockScript::new(), FEN);
+    let mut moved_piece = |mode: ColorMode| {
+        let chess_move = SquareColorEngine::same(mode)
+            .choose_move(&mut game)
+            .expect("Unable to choose move");
+
+        game.board.piece_on(chess_move.get_source())
+    };
+
+    assert_eq!(
+        moved_piece(ColorMode::Fixed(SquareColor::Light)),
+        Some(Piece::King),
+    );
+    assert_eq!(
+        moved_piece(ColorMode::Fixed(SquareColor::Dark)),
+        Some(Piece::Knight),
+    );
+    assert_eq!(moved_piece(ColorMode::King), Some(Piece::Knight));
+}
+
+#[test]
+fn leaves_king_color() {
+    use crate::backend::mock::MockScript;
+
+    // The king started on e8, a light square, so both it and the rook want dark ones
+    const FEN: &str = "4k3/8/8/8/8/8/7K/r7 b - - 0 1";
+
+    let mut game = Game::mock(MockScript::new(), FEN);
+    let chess_move = SquareColorEngine::opposite(ColorMode::King)
+        .choose_move(&mut game)
+        .expect("Unable to choose move");
+
+    assert!(
+        ["e8d8", "e8e7", "e8f8"].contains(&chess_move.to_string().as_str()),
+        "Chose {}",
+        chess_move,
+    );
+}
@@ -28,7 +28,21 @@ impl Engine for StockfishEngine {
         "Boring engine. Simply returns whatever Stockfish thinks is the best move."
     }
 
-    fn choose_move(&self, game: &mut Game) -> ChessMove {
-        game.stockfish.evaluate_position(&game.board).chess_move
+    fn needs_backend(&self) -> bool {
+        true
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
+        let scored_move = game.backend.evaluate_position(&game.board)?;
+        Ok(scored_move.chess_move)
+    }
+
+    fn choose_move_with_ponder(&self, game: &mut Game) -> Result<(ChessMove, Option<ChessMove>)> {
+        let (scored_move, ponder_move) = game.backend.evaluate_position_with_ponder(&game.board)?;
+        Ok((scored_move.chess_move, ponder_move))
+    }
+
+    fn choose_antichess_move(&self, game: &mut Game) -> Result<ChessMove> {
+        game.backend.evaluate_antichess_position(&game.antichess)
     }
 }
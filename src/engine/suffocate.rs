@@ -0,0 +1,72 @@
+/*
+ * engine/suffocate.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+use super::choose_best;
+use super::prelude::*;
+use crate::chess960;
+use chess::MoveGen;
+
+#[derive(Debug)]
+pub struct SuffocateEngine;
+
+impl Engine for SuffocateEngine {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Suffocate
+    }
+
+    fn name(&self) -> &'static str {
+        "Suffocate"
+    }
+
+    fn description(&self) -> &'static str {
+        "Leaves the opponent with as few legal replies as it can"
+    }
+
+    fn needs_backend(&self) -> bool {
+        false
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
+        // Checkmate and stalemate both leave no replies, so it likes them equally
+        let moves = game
+            .moves()
+            .map(|chess_move| {
+                let possible_board = chess960::make_move(&game.board, chess_move);
+
+                let replies = MoveGen::new_legal(&possible_board).len();
+                (chess_move, -(replies as i32))
+            })
+            .collect::<Vec<_>>();
+
+        log!(
+            game.log_file,
+            "Scored possible moves for suffocation: {:?}",
+            moves,
+        );
+
+        choose_best(moves, &mut game.rng)
+    }
+}
+
+#[test]
+fn chooses_checkmate() {
+    use crate::backend::mock::MockScript;
+
+    const FEN: &str = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+
+    let mut game = Game::mock(MockScript::new(), FEN);
+    let chess_move = SuffocateEngine
+        .choose_move(&mut game)
+        .expect("Unable to choose move");
+
+    assert_eq!(chess_move.to_string(), "a1a8");
+}
@@ -0,0 +1,86 @@
+/*
+ * engine/swarm.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+use super::choose_best;
+use super::prelude::*;
+use crate::chess960;
+use crate::squares::{total_distance, Metric};
+
+#[derive(Debug)]
+pub struct SwarmEngine(Metric);
+
+impl SwarmEngine {
+    pub fn new(metric: Metric) -> Self {
+        SwarmEngine(metric)
+    }
+}
+
+impl Default for SwarmEngine {
+    fn default() -> Self {
+        SwarmEngine::new(Metric::Chebyshev)
+    }
+}
+
+impl Engine for SwarmEngine {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Swarm
+    }
+
+    fn name(&self) -> &'static str {
+        "Swarm"
+    }
+
+    fn description(&self) -> &'static str {
+        "Crowds its pieces as close to the opponent's king as it can"
+    }
+
+    fn needs_backend(&self) -> bool {
+        false
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
+        let color = game.board.side_to_move();
+        let king = game.board.king_square(!color);
+        let moves = game
+            .moves()
+            .map(|chess_move| {
+                let possible_board = chess960::make_move(&game.board, chess_move);
+                let distance = total_distance(&possible_board, color, king, self.0);
+
+                (chess_move, -(distance as i32))
+            })
+            .collect::<Vec<_>>();
+
+        log!(
+            game.log_file,
+            "Scored possible moves for swarming: {:?}",
+            moves,
+        );
+
+        choose_best(moves, &mut game.rng)
+    }
+}
+
+#[test]
+fn moves_towards_enemy_king() {
+    use crate::backend::mock::MockScript;
+
+    // The rook gets closest by going all the way up the file
+    const FEN: &str = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1";
+
+    let mut game = Game::mock(MockScript::new(), FEN);
+    let chess_move = SwarmEngine::new(Metric::Manhattan)
+        .choose_move(&mut game)
+        .expect("Unable to choose move");
+
+    assert_eq!(chess_move.to_string(), "a1a8");
+}
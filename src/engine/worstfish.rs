@@ -28,14 +28,44 @@ impl Engine for WorstfishEngine {
         "Chooses the move Stockfish dislikes the most."
     }
 
-    fn choose_move(&self, game: &mut Game) -> ChessMove {
+    fn needs_backend(&self) -> bool {
+        true
+    }
+
+    fn choose_move(&self, game: &mut Game) -> Result<ChessMove> {
         // Choose the move with the lowest score
         // Since this is sorted in increasing order, the worst move should be the first one.
 
-        game.stockfish
-            .evaluate_possible_moves(&game.board)
+        game.backend
+            .evaluate_possible_moves(&game.board)?
             .first()
-            .expect("No legal moves")
-            .chess_move
+            .map(|scored_move| scored_move.chess_move)
+            .ok_or(Error::NoLegalMoves)
     }
 }
+
+#[test]
+fn chooses_worst_move() {
+    use crate::backend::mock::MockScript;
+
+    const FEN: &str = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+
+    let script = MockScript::new().position(
+        FEN,
+        &[
+            ("e2e4", 50),
+            ("e2e3", 30),
+            ("e1d2", 10),
+            ("e1f2", 0),
+            ("e1d1", -20),
+            ("e1f1", -40),
+        ],
+    );
+
+    let mut game = Game::mock(script, FEN);
+    let chess_move = WorstfishEngine
+        .choose_move(&mut game)
+        .expect("Unable to choose move");
+
+    assert_eq!(chess_move.to_string(), "e1f1");
+}
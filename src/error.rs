@@ -0,0 +1,133 @@
+/*
+ * error.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! The error type for everything which can fail during a game.
+
+use crate::game::Variant;
+use std::fmt::{self, Display};
+use std::io;
+use std::path::PathBuf;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading from or writing to a pipe or file failed.
+    Io(io::Error),
+
+    /// The backend engine process could not be started.
+    BackendSpawn(io::Error),
+
+    /// The backend engine closed its output, likely because it crashed.
+    BackendExited,
+
+    /// The backend engine stopped responding.
+    BackendTimeout,
+
+    /// The backend engine chose a move without ever sending a score.
+    BackendMissingScore,
+
+    /// The evaluation cache file is not one we can read.
+    InvalidCache(&'static str),
+
+    /// The GUI sent a FEN we could not parse.
+    InvalidFen(String),
+
+    /// The GUI sent a `position` command which doesn't make sense.
+    InvalidPosition(&'static str),
+
+    /// The requested engine doesn't exist.
+    UnknownEngine(String),
+
+    /// The configuration file couldn't be read, or has settings we don't understand.
+    InvalidConfigFile { path: PathBuf, reason: String },
+
+    /// A command-line argument had an invalid value.
+    InvalidArgument {
+        name: &'static str,
+        value: String,
+        reason: String,
+    },
+
+    /// The engine chose a move which isn't legal in this position.
+    IllegalMove(String),
+
+    /// There are no legal moves, so the game is already over.
+    NoLegalMoves,
+
+    /// The engine doesn't know how to play the current chess variant.
+    UnsupportedVariant(Variant),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "I/O error: {}", error),
+            Error::BackendSpawn(error) => write!(f, "Unable to start backend engine: {}", error),
+            Error::BackendExited => write!(f, "Backend engine exited unexpectedly"),
+            Error::BackendTimeout => write!(f, "Backend engine stopped responding"),
+            Error::BackendMissingScore => {
+                write!(f, "Backend engine didn't send a score before its best move")
+            }
+            Error::InvalidCache(reason) => write!(f, "Invalid evaluation cache file: {}", reason),
+            Error::InvalidFen(fen) => write!(f, "Invalid FEN: {}", fen),
+            Error::InvalidPosition(reason) => write!(f, "Invalid position: {}", reason),
+            Error::UnknownEngine(name) => write!(f, "Unknown game engine: {}", name),
+            Error::InvalidConfigFile { path, reason } => {
+                write!(
+                    f,
+                    "Invalid configuration file {}: {}",
+                    path.display(),
+                    reason
+                )
+            }
+            Error::InvalidArgument {
+                name,
+                value,
+                reason,
+            } => write!(f, "Invalid {}: {} {}", name, value, reason),
+            Error::IllegalMove(chess_move) => {
+                write!(f, "Engine chose illegal move: {}", chess_move)
+            }
+            Error::NoLegalMoves => write!(f, "No legal moves"),
+            Error::UnsupportedVariant(variant) => {
+                write!(f, "Engine does not support variant: {}", variant.uci_name())
+            }
+        }
+    }
+}
+
+impl Error {
+    /// Whether this error means the backend process is dead or hung, and should be restarted.
+    pub fn is_backend_failure(&self) -> bool {
+        matches!(
+            self,
+            Error::Io(_) | Error::BackendExited | Error::BackendTimeout,
+        )
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) | Error::BackendSpawn(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    #[inline]
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
@@ -0,0 +1,983 @@
+/*
+ * game/mod.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+mod xboard;
+
+use crate::antichess::{self, AntichessBoard};
+use crate::backend::{Backend, SearchControl};
+use crate::cache::{DiskCache, EvaluationCache};
+use crate::chess960::{self, Castling};
+use crate::clock::TimeControl;
+use crate::config::Configuration;
+use crate::engine::Engine;
+use crate::error::{Error, Result};
+use crate::history::History;
+use crate::options;
+use chess::{Board, ChessMove, Color, MoveGen};
+use rand::prelude::*;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use vampirc_uci::{parse_one, UciFen, UciInfoAttribute, UciMessage, UciTimeControl};
+
+/// Which protocol to speak with the GUI.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Protocol {
+    Uci,
+    Xboard,
+}
+
+impl Protocol {
+    /// Uses the configured protocol, or otherwise guesses it from the first line the GUI sends.
+    ///
+    /// xboard always starts by sending `xboard`, while UCI GUIs start with `uci`.
+    fn detect(configured: Option<Protocol>, first_line: &str) -> Self {
+        configured.unwrap_or_else(|| match first_line.trim() {
+            "xboard" => Protocol::Xboard,
+            _ => Protocol::Uci,
+        })
+    }
+}
+
+/// Which rules the game is played by.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Variant {
+    Standard,
+    Antichess,
+}
+
+impl Variant {
+    /// The name of the variant in the `UCI_Variant` option.
+    pub fn uci_name(self) -> &'static str {
+        match self {
+            Variant::Standard => "chess",
+            Variant::Antichess => "antichess",
+        }
+    }
+}
+
+/// Where messages to the GUI are written.
+#[derive(Debug)]
+enum Output {
+    Stdout,
+
+    #[cfg(test)]
+    Channel(mpsc::Sender<String>),
+}
+
+#[derive(Debug)]
+pub struct Game {
+    pub board: Board,
+
+    /// The position this game started from, before any of its moves.
+    pub start: Board,
+    pub history: History,
+    pub castling: Castling,
+    pub antichess: AntichessBoard,
+    pub backend: Backend,
+    pub rng: StdRng,
+    seed: u64,
+    game_number: u64,
+    protocol: Protocol,
+    control: SearchControl,
+    input: Receiver<String>,
+    output: Output,
+    pub config: Configuration,
+    pub log_file: Arc<File>,
+}
+
+impl Game {
+    // Constructor
+    pub fn new(config: Configuration) -> Result<Self> {
+        let control = SearchControl::new();
+        let input = read_input(
+            BufReader::new(io::stdin()).lines(),
+            control.clone(),
+            config.protocol,
+            Arc::clone(&config.log_file),
+        );
+
+        Game::with_input(config, control, input)
+    }
+
+    /// Sets up a game which receives commands from `input`, rather than standard input.
+    fn with_input(
+        config: Configuration,
+        control: SearchControl,
+        input: Receiver<String>,
+    ) -> Result<Self> {
+        let log_file = Arc::clone(&config.log_file);
+
+        let mut cache = EvaluationCache::new(config.cache_size);
+        if let Some(ref path) = config.cache_file {
+            let disk_cache = DiskCache::open(path, &config.backend, &config.backend_options)?;
+
+            log!(
+                log_file,
+                "Loaded {} evaluations from cache file {}",
+                disk_cache.len(),
+                path.display(),
+            );
+
+            cache = cache.with_disk(disk_cache);
+        }
+
+        // Engines which only look at the board never need the backend
+        let mut backend = Backend::new(&config, cache, control.clone());
+        if config.engine_kind.build(&config).needs_backend() {
+            backend.start()?;
+        }
+
+        let seed = config.seed.unwrap_or_else(|| thread_rng().gen());
+        log!(log_file, "Using random seed {}", seed);
+
+        Ok(Game {
+            board: Board::default(),
+            start: Board::default(),
+            history: History::new(&Board::default(), 0),
+            castling: Castling::from_board(&Board::default()),
+            antichess: AntichessBoard::default(),
+            backend,
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            game_number: 0,
+            protocol: Protocol::Uci,
+            control,
+            input,
+            output: Output::Stdout,
+            config,
+            log_file,
+        })
+    }
+
+    // Communication
+    fn receive_line(&mut self) -> String {
+        // The input thread sends a quit before it stops, so this is only a fallback
+        self.input.recv().unwrap_or_else(|_| "quit".into())
+    }
+
+    #[inline]
+    fn receive(&mut self) -> UciMessage {
+        parse_one(&self.receive_line())
+    }
+
+    fn send<D: Display>(&mut self, command: D) {
+        log!(self.log_file, "Sending message: {}", command);
+
+        match self.output {
+            Output::Stdout => println!("{}", command),
+
+            #[cfg(test)]
+            Output::Channel(ref sender) => {
+                let _ = sender.send(command.to_string());
+            }
+        }
+    }
+
+    /// Reports an error to the GUI without stopping the game.
+    fn send_error(&mut self, error: &Error) {
+        log!(self.log_file, "Error: {}", error);
+
+        match self.protocol {
+            Protocol::Uci => self.send(UciMessage::Info(vec![UciInfoAttribute::String(
+                error.to_string(),
+            )])),
+            Protocol::Xboard => self.send(format_args!("telluser {}", error)),
+        }
+    }
+
+    // Execution
+    pub fn main_loop(&mut self) -> Result<()> {
+        let first_line = self.receive_line();
+        self.protocol = Protocol::detect(self.config.protocol, &first_line);
+        log!(
+            self.log_file,
+            "Starting game main loop (protocol {:?})",
+            self.protocol,
+        );
+
+        match self.protocol {
+            Protocol::Uci => self.uci_loop(first_line),
+            Protocol::Xboard => self.xboard_loop(first_line),
+        }
+    }
+
+    fn uci_loop(&mut self, first_line: String) -> Result<()> {
+        let mut engine = self.build_engine();
+        let mut first_line = Some(first_line);
+
+        loop {
+            let line = match first_line.take() {
+                Some(line) => line,
+                None => self.receive_line(),
+            };
+
+            match parse_one(&line) {
+                // Set game state
+                UciMessage::UciNewGame => {
+                    log!(self.log_file, "Resetting game state");
+                    self.reset();
+                }
+
+                // The UCI parser drops promotions to a king, so antichess moves are read ourselves
+                UciMessage::Position { .. } if self.config.variant == Variant::Antichess => {
+                    let result = parse_position(&line)
+                        .and_then(|(startpos, fen, moves)| self.set_position(startpos, fen, moves));
+
+                    if let Err(error) = result {
+                        self.send_error(&error);
+                    }
+                }
+
+                UciMessage::Position {
+                    startpos,
+                    fen,
+                    moves,
+                } => {
+                    if let Err(error) = self.set_position(startpos, fen, moves) {
+                        self.send_error(&error);
+                    }
+                }
+
+                // Request move decision from engine
+                UciMessage::Go { time_control, .. } => {
+                    log!(
+                        self.log_file,
+                        "Engine decision has been requested (pondering {})",
+                        self.control.is_pondering(),
+                    );
+                    log!(
+                        self.log_file,
+                        "Halfmove clock {}, repeated position {}, draw claimable {}",
+                        self.history.halfmove_clock(),
+                        self.is_repetition(),
+                        self.can_claim_draw(),
+                    );
+
+                    let infinite = time_control == Some(UciTimeControl::Infinite);
+                    let budget = time_control
+                        .and_then(|time_control| {
+                            TimeControl::from_uci(&time_control, self.side_to_move())
+                        })
+                        .and_then(|time_control| time_control.budget());
+
+                    self.backend.set_time_budget(budget);
+
+                    if !self.decide_move(&*engine, infinite) {
+                        log!(self.log_file, "Received quit request while searching");
+                        break;
+                    }
+                }
+
+                // Status messages
+                UciMessage::Uci => {
+                    log!(self.log_file, "Received UCI startup message");
+                    self.send(UciMessage::Id {
+                        name: Some(format!("{:?} ({})", engine.kind(), env!("CARGO_PKG_NAME"))),
+                        author: None,
+                    });
+                    self.send(UciMessage::Id {
+                        name: None,
+                        author: Some(env!("CARGO_PKG_AUTHORS").into()),
+                    });
+
+                    for option in options::uci_options(&self.config) {
+                        self.send(UciMessage::Option(option));
+                    }
+
+                    self.send(UciMessage::UciOk);
+                }
+
+                UciMessage::SetOption { name, value } => {
+                    log!(self.log_file, "Setting option {} = {:?}", name, value);
+
+                    match options::set_option(&mut self.config, &name, value.as_deref()) {
+                        Ok(()) => {
+                            self.backend.set_nodes_to_search(self.config.backend_nodes);
+                            self.backend
+                                .set_evaluation_mode(self.config.evaluation_mode);
+                            engine = self.build_engine();
+
+                            if engine.needs_backend() {
+                                if let Err(error) = self.backend.start() {
+                                    self.send_error(&error);
+                                }
+                            }
+                        }
+                        Err(error) => {
+                            log!(self.log_file, "Unable to set option: {}", error);
+                            self.send(UciMessage::Info(vec![UciInfoAttribute::String(error)]));
+                        }
+                    }
+                }
+
+                UciMessage::IsReady => {
+                    log!(self.log_file, "Engine readiness requested");
+                    self.send(UciMessage::ReadyOk);
+                }
+
+                // Terminal messages
+                UciMessage::Quit => {
+                    log!(self.log_file, "Received quit request, breaking main loop");
+                    break;
+                }
+
+                // The UCI parser only accepts KQkq castling rights, so read Shredder-FENs ourselves
+                UciMessage::Unknown(line, _) if line.starts_with("position ") => {
+                    let result = parse_position(&line)
+                        .and_then(|(startpos, fen, moves)| self.set_position(startpos, fen, moves));
+
+                    if let Err(error) = result {
+                        self.send_error(&error);
+                    }
+                }
+
+                // Ignore unknown or unexpected messages
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build_engine(&self) -> Box<dyn Engine> {
+        let engine = self.config.engine_kind.build(&self.config);
+
+        log!(self.log_file, "Engine: {:?}", engine.kind());
+
+        engine
+    }
+
+    pub fn reset(&mut self) {
+        self.board = Board::default();
+        self.start = self.board;
+        self.history = History::new(&self.board, 0);
+        self.castling = Castling::from_board(&self.board);
+        self.antichess = AntichessBoard::default();
+
+        // Each game gets its own sequence, so any one of them can be replayed from the seed
+        self.game_number += 1;
+        self.rng = StdRng::seed_from_u64(self.seed.wrapping_add(self.game_number));
+        log!(
+            self.log_file,
+            "Starting game {} with random seed {}",
+            self.game_number,
+            self.seed,
+        );
+    }
+
+    fn set_position(
+        &mut self,
+        startpos: bool,
+        fen: Option<UciFen>,
+        moves: Vec<ChessMove>,
+    ) -> Result<()> {
+        log!(
+            self.log_file,
+            "Setting board position ({}, moves {})",
+            match fen {
+                Some(ref fen) => &fen.0,
+                None => "start",
+            },
+            moves.len(),
+        );
+
+        if self.config.variant == Variant::Antichess {
+            return self.set_antichess_position(startpos, fen, moves);
+        }
+
+        let (mut board, mut castling, halfmove_clock) = match (startpos, fen) {
+            (true, None) => (Board::default(), Castling::from_board(&Board::default()), 0),
+            (false, Some(fen)) => {
+                // Chess960 positions may use Shredder-FEN or X-FEN castling rights
+                let (board, castling) = chess960::parse_fen(&fen.0)?;
+
+                // The chess crate doesn't keep the halfmove clock, so read it ourselves
+                let halfmove_clock = match fen.0.split_whitespace().nth(4) {
+                    Some(value) => value.parse().map_err(|_| Error::InvalidFen(fen.0))?,
+                    None => 0,
+                };
+
+                (board, castling, halfmove_clock)
+            }
+            _ => return Err(Error::InvalidPosition("inconsistent startpos and fen")),
+        };
+
+        let start = board;
+        let mut history = History::new(&board, halfmove_clock);
+
+        for m in moves {
+            let m = self.read_move(&board, m);
+            if !castling.is_legal(&board, m) {
+                return Err(Error::InvalidPosition("illegal move in move list"));
+            }
+
+            castling.update(&board, m);
+            history.push(&board, m);
+            board = chess960::make_move(&board, m);
+        }
+
+        // Only replace the board once we know the whole position is valid
+        self.board = board;
+        self.start = start;
+        self.history = history;
+        self.castling = castling;
+        Ok(())
+    }
+
+    fn set_antichess_position(
+        &mut self,
+        startpos: bool,
+        fen: Option<UciFen>,
+        moves: Vec<ChessMove>,
+    ) -> Result<()> {
+        let mut board = match (startpos, fen) {
+            (true, None) => AntichessBoard::default(),
+            (false, Some(fen)) => AntichessBoard::from_fen(&fen.0)?,
+            _ => return Err(Error::InvalidPosition("inconsistent startpos and fen")),
+        };
+
+        for m in moves {
+            if !board.legal(m) {
+                return Err(Error::InvalidPosition("illegal move in move list"));
+            }
+
+            board = board.make_move_new(m);
+        }
+
+        self.antichess = board;
+        Ok(())
+    }
+
+    /// Runs the engine and sends its move, returning `false` if the GUI asked us to quit.
+    fn decide_move(&mut self, engine: &dyn Engine, infinite: bool) -> bool {
+        let chosen_move = self.choose_move(engine);
+        let keep_running = self.wait_for_release(infinite);
+
+        match chosen_move {
+            Some((chosen_move, ponder_move)) => {
+                let best_move = self.write_move(chosen_move);
+                self.play(chosen_move);
+
+                // Only suggest replies the GUI can actually ponder on
+                let ponder_move = ponder_move
+                    .filter(|_| self.config.ponder)
+                    .filter(|&ponder_move| self.castling.is_legal(&self.board, ponder_move))
+                    .map(|ponder_move| self.write_move(ponder_move));
+
+                self.send(UciMessage::BestMove {
+                    best_move,
+                    ponder: ponder_move,
+                });
+            }
+            None => {
+                // The game is already over, so there is no move to send.
+                // The UCI null move lets the GUI know we're done searching.
+                self.send("bestmove 0000");
+            }
+        }
+
+        keep_running
+    }
+
+    /// Asks the engine for its move and a reply to ponder on.
+    ///
+    /// If the engine fails, any legal move is chosen instead of forfeiting.
+    /// Returns `None` if there are no legal moves.
+    fn choose_move(&mut self, engine: &dyn Engine) -> Option<(ChessMove, Option<ChessMove>)> {
+        let chess960 = if self.config.chess960 {
+            Some(self.castling)
+        } else {
+            None
+        };
+        self.backend.set_chess960(chess960);
+
+        // A misbehaving backend could suggest anything
+        let result = match self.config.variant {
+            Variant::Standard => {
+                engine
+                    .choose_move_with_ponder(self)
+                    .and_then(|(chess_move, ponder_move)| {
+                        if self.castling.is_legal(&self.board, chess_move) {
+                            Ok((chess_move, ponder_move))
+                        } else {
+                            Err(Error::IllegalMove(chess_move.to_string()))
+                        }
+                    })
+            }
+            Variant::Antichess => engine.choose_antichess_move(self).and_then(|chess_move| {
+                if self.antichess.legal(chess_move) {
+                    Ok((chess_move, None))
+                } else {
+                    Err(Error::IllegalMove(chess_move.to_string()))
+                }
+            }),
+        };
+
+        match result {
+            Ok(chosen_move) => Some(chosen_move),
+            Err(error) => {
+                // Rather than forfeiting, play any legal move
+                self.send_error(&error);
+
+                let fallback_move = match self.config.variant {
+                    Variant::Standard => self.moves().next(),
+                    Variant::Antichess => self.antichess.legal_moves().first().copied(),
+                };
+                if let Some(chess_move) = fallback_move {
+                    log!(self.log_file, "Playing fallback move {}", chess_move);
+                }
+
+                fallback_move.map(|chess_move| (chess_move, None))
+            }
+        }
+    }
+
+    /// Holds on to our move until the GUI ends an infinite or ponder search.
+    ///
+    /// Returns `false` if the GUI asked us to quit while waiting.
+    fn wait_for_release(&mut self, infinite: bool) -> bool {
+        while (infinite && !self.control.is_stopped()) || self.control.is_pondering() {
+            match self.receive() {
+                UciMessage::IsReady => self.send(UciMessage::ReadyOk),
+                UciMessage::Quit => return false,
+
+                // Stop and ponderhit have already been applied by the input thread
+                _ => (),
+            }
+        }
+
+        true
+    }
+
+    // Methods
+
+    /// Lists the legal moves, including castling moves the chess crate can't make in Chess960.
+    ///
+    /// Those moves have to be made with `chess960::make_move()`.
+    pub fn moves(&self) -> impl Iterator<Item = ChessMove> {
+        MoveGen::new_legal(&self.board).chain(self.castling.moves(&self.board))
+    }
+
+    /// Whose turn it is, on the board of the variant being played.
+    pub fn side_to_move(&self) -> Color {
+        match self.config.variant {
+            Variant::Standard => self.board.side_to_move(),
+            Variant::Antichess => self.antichess.side_to_move(),
+        }
+    }
+
+    /// Makes a move on the board, recording it in the history.
+    pub fn play(&mut self, chess_move: ChessMove) {
+        if self.config.variant == Variant::Antichess {
+            self.antichess = self.antichess.make_move_new(chess_move);
+            return;
+        }
+
+        self.castling.update(&self.board, chess_move);
+        self.history.push(&self.board, chess_move);
+        self.board = chess960::make_move(&self.board, chess_move);
+    }
+
+    /// Reads a move from the GUI, which writes castling as the king taking its rook in Chess960.
+    fn read_move(&self, board: &Board, chess_move: ChessMove) -> ChessMove {
+        if self.config.chess960 {
+            chess960::from_chess960(board, chess_move)
+        } else {
+            chess_move
+        }
+    }
+
+    /// Writes a move from the current position the way the GUI expects it.
+    fn write_move(&self, chess_move: ChessMove) -> ChessMove {
+        if self.config.chess960 && self.config.variant == Variant::Standard {
+            chess960::to_chess960(&self.board, chess_move)
+        } else {
+            chess_move
+        }
+    }
+
+    /// Whether the current position has occurred before in this game.
+    #[inline]
+    pub fn is_repetition(&self) -> bool {
+        self.history.is_repetition()
+    }
+
+    /// Whether a draw can be claimed, by threefold repetition or the fifty-move rule.
+    #[inline]
+    pub fn can_claim_draw(&self) -> bool {
+        self.history.can_claim_draw()
+    }
+}
+
+/// Parses a `position` command, for those the UCI parser rejects.
+fn parse_position(line: &str) -> Result<(bool, Option<UciFen>, Vec<ChessMove>)> {
+    let invalid = || Error::InvalidPosition("unreadable position command");
+    let mut words = line.split_whitespace().skip(1);
+
+    let (startpos, fen) = match words.next() {
+        Some("startpos") => (true, None),
+        Some("fen") => {
+            let fen = words
+                .by_ref()
+                .take_while(|&word| word != "moves")
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            (false, Some(UciFen(fen)))
+        }
+        _ => return Err(invalid()),
+    };
+
+    let moves = words
+        .filter(|&word| word != "moves")
+        .map(|word| antichess::parse_move(word).map_err(|_| invalid()))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok((startpos, fen, moves))
+}
+
+/// Reads commands from the GUI on a separate thread, so a running search can be stopped.
+///
+/// Commands which affect a search are applied to `control` right away,
+/// then every line is passed on to the main loop in order.
+fn read_input<I>(
+    lines: I,
+    control: SearchControl,
+    protocol: Option<Protocol>,
+    log_file: Arc<File>,
+) -> Receiver<String>
+where
+    I: Iterator<Item = io::Result<String>> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut protocol = protocol;
+
+        // A ponder search without a time limit has to end on ponderhit, since we don't know our clock
+        let mut unlimited_ponder = false;
+
+        for line in lines {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => {
+                    log!(log_file, "Unable to read input: {}", error);
+                    break;
+                }
+            };
+
+            log!(log_file, "Receiving message: {}", line);
+
+            match *protocol.get_or_insert_with(|| Protocol::detect(None, &line)) {
+                Protocol::Uci => match parse_one(&line) {
+                    UciMessage::Go { time_control, .. } => {
+                        // The parser drops "ponder" if a clock is also given, so check for it ourselves
+                        let pondering = line.split_whitespace().any(|word| word == "ponder");
+                        unlimited_ponder = pondering
+                            && matches!(time_control, None | Some(UciTimeControl::Ponder));
+
+                        control.start(pondering);
+                    }
+                    UciMessage::PonderHit if unlimited_ponder => control.stop(),
+                    UciMessage::PonderHit => control.ponderhit(),
+                    UciMessage::Stop | UciMessage::Quit => control.stop(),
+                    _ => (),
+                },
+                Protocol::Xboard => xboard::apply_control(&line, &control),
+            }
+
+            if sender.send(line).is_err() {
+                return;
+            }
+        }
+
+        // If the GUI closed our input, there's nothing left to do
+        log!(log_file, "Input closed, treating as quit");
+        control.stop();
+        let _ = sender.send("quit".into());
+    });
+
+    receiver
+}
+
+#[cfg(test)]
+impl Game {
+    /// Starts a game from the given position, using the mock backend.
+    pub fn mock(script: crate::backend::mock::MockScript, fen: &str) -> Self {
+        use crate::engine::EngineKind;
+
+        Game::mock_config(Configuration::mock(EngineKind::Random, script), fen)
+    }
+
+    /// Like `mock()`, but with any configuration.
+    pub fn mock_config(config: Configuration, fen: &str) -> Self {
+        // Tests drive the game directly, so it has no input
+        let (_, input) = mpsc::channel();
+        let mut game = Game::with_input(config, SearchControl::new(), input)
+            .expect("Unable to start mock game");
+        game.set_position(false, Some(UciFen(fen.into())), Vec::new())
+            .expect("Invalid FEN");
+        game
+    }
+}
+
+/// A game running on its own thread, which tests talk to like a GUI would.
+#[cfg(test)]
+pub struct Session {
+    input: mpsc::Sender<String>,
+    output: Receiver<String>,
+    thread: Option<thread::JoinHandle<Result<()>>>,
+}
+
+#[cfg(test)]
+impl Session {
+    /// How long to wait for a reply before failing the test.
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+    pub fn start(config: Configuration) -> Self {
+        let (input, lines) = mpsc::channel();
+        let control = SearchControl::new();
+        let receiver = read_input(
+            lines.into_iter().map(Ok),
+            control.clone(),
+            config.protocol,
+            Arc::clone(&config.log_file),
+        );
+
+        let (sender, output) = mpsc::channel();
+        let mut game =
+            Game::with_input(config, control, receiver).expect("Unable to start mock game");
+        game.output = Output::Channel(sender);
+
+        Session {
+            input,
+            output,
+            thread: Some(thread::spawn(move || game.main_loop())),
+        }
+    }
+
+    pub fn send(&self, line: &str) {
+        self.input
+            .send(line.into())
+            .expect("Game stopped reading input");
+    }
+
+    pub fn receive(&self) -> String {
+        self.output
+            .recv_timeout(Session::TIMEOUT)
+            .expect("No reply from game")
+    }
+
+    /// Skips replies until one starts with `prefix`, returning it.
+    pub fn receive_until(&self, prefix: &str) -> String {
+        loop {
+            let line = self.receive();
+            if line.starts_with(prefix) {
+                return line;
+            }
+        }
+    }
+
+    /// Checks that the game doesn't reply for a while.
+    pub fn assert_quiet(&self, duration: std::time::Duration) {
+        if let Ok(line) = self.output.recv_timeout(duration) {
+            panic!("Unexpected reply from game: {}", line);
+        }
+    }
+
+    pub fn quit(mut self) {
+        self.send("quit");
+        self.thread
+            .take()
+            .expect("Game already stopped")
+            .join()
+            .expect("Game thread panicked")
+            .expect("Game main loop failed");
+    }
+}
+
+#[test]
+fn falls_back_on_backend_failure() {
+    use crate::backend::mock::{Failure, MockScript};
+    use crate::engine::StockfishEngine;
+
+    const FEN: &str = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+
+    // The backend crashes on every search, and there are no retries
+    let script = MockScript::new().fail(Failure::Crash, usize::MAX);
+    let mut game = Game::mock(script, FEN);
+    let (chess_move, ponder_move) = game
+        .choose_move(&StockfishEngine)
+        .expect("No fallback move chosen");
+
+    assert!(game.board.legal(chess_move), "Illegal move {}", chess_move);
+    assert_eq!(ponder_move, None);
+}
+
+#[test]
+fn falls_back_after_retries() {
+    use crate::backend::mock::{Failure, MockScript};
+    use crate::backend::Supervision;
+    use crate::engine::{EngineKind, StockfishEngine};
+    use std::time::Duration;
+
+    const FEN: &str = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+
+    for &failure in &[Failure::Crash, Failure::Hang, Failure::Malformed] {
+        let script = MockScript::new().fail(failure, usize::MAX);
+        let mut config = Configuration::mock(EngineKind::Stockfish, script);
+        config.backend_nodes = Some(1000);
+        config.supervision = Supervision {
+            timeout: Some(Duration::from_millis(100)),
+            max_retries: 2,
+        };
+
+        let mut game = Game::mock_config(config, FEN);
+        let (chess_move, _) = game
+            .choose_move(&StockfishEngine)
+            .expect("No fallback move chosen");
+
+        assert!(game.board.legal(chess_move), "Illegal move {}", chess_move);
+    }
+}
+
+#[test]
+fn chess960_castling_moves() {
+    use crate::backend::mock::MockScript;
+    use crate::engine::{Engine, SuffocateEngine};
+    use std::str::FromStr;
+
+    // The king and rooks don't start on their usual squares
+    const FEN: &str = "1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/1R3KR1 w GBgb - 0 1";
+
+    let mut game = Game::mock(MockScript::new(), FEN);
+    let castles = ["f1g1", "f1b1"]
+        .iter()
+        .map(|text| ChessMove::from_str(text).expect("Invalid move"))
+        .collect::<Vec<_>>();
+
+    let moves = game.moves().collect::<Vec<_>>();
+    for chess_move in &castles {
+        assert!(moves.contains(chess_move), "Missing {}", chess_move);
+    }
+
+    // Engines which pick from these moves can castle too
+    let chess_move = SuffocateEngine
+        .choose_move(&mut game)
+        .expect("Unable to choose move");
+    assert!(game.castling.is_legal(&game.board, chess_move));
+}
+
+#[test]
+fn antichess_side_to_move() {
+    use crate::backend::mock::MockScript;
+    use crate::engine::EngineKind;
+
+    const FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1";
+
+    let mut config = Configuration::mock(EngineKind::Random, MockScript::new());
+    config.variant = Variant::Antichess;
+
+    // The standard board isn't kept up to date in antichess, so the clock has to come from this one
+    let mut game = Game::mock_config(config, FEN);
+    let e2e3 = antichess::parse_move("e2e3").expect("Invalid move");
+    game.set_position(true, None, vec![e2e3])
+        .expect("Unable to set position");
+
+    assert_eq!(game.board.side_to_move(), Color::White);
+    assert_eq!(game.side_to_move(), Color::Black);
+}
+
+#[test]
+fn stop_ends_search() {
+    use crate::backend::mock::MockScript;
+    use crate::engine::EngineKind;
+    use std::time::{Duration, Instant};
+
+    let session = Session::start(Configuration::mock(
+        EngineKind::Stockfish,
+        MockScript::new(),
+    ));
+    session.send("uci");
+    session.receive_until("uciok");
+    session.send("position startpos");
+
+    // The mock uses the whole movetime unless it's told to stop
+    session.send("go movetime 5000");
+    session.assert_quiet(Duration::from_millis(100));
+
+    let stopped = Instant::now();
+    session.send("stop");
+    session.receive_until("bestmove");
+    assert!(stopped.elapsed() < Duration::from_secs(1));
+
+    session.quit();
+}
+
+#[test]
+fn ponderhit_releases_move() {
+    use crate::backend::mock::MockScript;
+    use crate::engine::EngineKind;
+    use std::time::{Duration, Instant};
+
+    let session = Session::start(Configuration::mock(
+        EngineKind::Stockfish,
+        MockScript::new(),
+    ));
+    session.send("position startpos moves e2e4 e7e5");
+
+    // The search finishes quickly, but the move is held until the opponent moves
+    session.send("go ponder wtime 5000 btime 5000");
+    session.assert_quiet(Duration::from_millis(500));
+
+    let hit = Instant::now();
+    session.send("ponderhit");
+    session.receive_until("bestmove");
+    assert!(hit.elapsed() < Duration::from_secs(1));
+
+    // Afterwards, searches on the clock are no longer held
+    session.send("go wtime 5000 btime 5000");
+    session.receive_until("bestmove");
+
+    session.quit();
+}
+
+#[test]
+fn infinite_waits_for_stop() {
+    use crate::backend::mock::MockScript;
+    use crate::engine::EngineKind;
+    use std::time::{Duration, Instant};
+
+    let session = Session::start(Configuration::mock(
+        EngineKind::Stockfish,
+        MockScript::new(),
+    ));
+    session.send("position startpos");
+
+    session.send("go infinite");
+    session.assert_quiet(Duration::from_millis(200));
+
+    // Pings are still answered while the move is held
+    session.send("isready");
+    assert_eq!(session.receive(), "readyok");
+
+    let stopped = Instant::now();
+    session.send("stop");
+    session.receive_until("bestmove");
+    assert!(stopped.elapsed() < Duration::from_secs(1));
+
+    session.quit();
+}
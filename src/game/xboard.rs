@@ -0,0 +1,466 @@
+/*
+ * game/xboard.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Frontend for the xboard / WinBoard protocol (CECP version 2).
+//!
+//! Unlike UCI, the GUI doesn't ask for a move each turn. Instead it tells us
+//! which side we play, sends the opponent's moves as they happen, and we reply
+//! with our own whenever it's our turn (unless we're in force mode).
+
+use super::Game;
+use crate::backend::SearchControl;
+use crate::chess960::{self, Side};
+use crate::clock::TimeControl;
+use crate::engine::Engine;
+use crate::error::Result;
+use chess::{BoardStatus, ChessMove, Color};
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Duration;
+use vampirc_uci::UciFen;
+
+/// The time control xboard uses until it sends `level`: 40 moves in 5 minutes.
+const DEFAULT_MOVES_PER_CONTROL: u32 = 40;
+const DEFAULT_BASE_TIME: Duration = Duration::from_secs(5 * 60);
+
+/// State only needed while speaking xboard.
+#[derive(Debug)]
+struct Xboard {
+    /// In force mode, we only play the moves we're given and never think.
+    force: bool,
+
+    /// Which side we're playing.
+    engine_color: Color,
+
+    /// How many moves per time control, or 0 if the base time is for the whole game.
+    moves_per_control: u32,
+
+    /// Time for each time control.
+    base_time: Duration,
+
+    /// Time added after each move.
+    increment: Duration,
+
+    /// Exact time to spend on each move, from `st`.
+    move_time: Option<Duration>,
+
+    /// Time left on our clock, from `time`.
+    remaining: Option<Duration>,
+
+    /// How many moves we've made this game.
+    moves_played: u32,
+}
+
+impl Xboard {
+    fn new() -> Self {
+        Xboard {
+            force: false,
+            engine_color: Color::Black,
+            moves_per_control: DEFAULT_MOVES_PER_CONTROL,
+            base_time: DEFAULT_BASE_TIME,
+            increment: Duration::default(),
+            move_time: None,
+            remaining: None,
+            moves_played: 0,
+        }
+    }
+
+    fn time_control(&self) -> TimeControl {
+        if self.move_time.is_some() {
+            return TimeControl {
+                move_time: self.move_time,
+                ..TimeControl::default()
+            };
+        }
+
+        let moves_to_go = match self.moves_per_control {
+            0 => None,
+            moves => Some(moves - self.moves_played % moves),
+        };
+
+        TimeControl {
+            remaining: Some(self.remaining.unwrap_or(self.base_time)),
+            increment: Some(self.increment),
+            moves_to_go,
+            move_time: None,
+        }
+    }
+}
+
+impl Game {
+    pub(super) fn xboard_loop(&mut self, first_line: String) -> Result<()> {
+        let mut engine = self.build_engine();
+        let mut state = Xboard::new();
+        let mut first_line = Some(first_line);
+
+        loop {
+            let line = match first_line.take() {
+                Some(line) => line,
+                None => self.receive_line(),
+            };
+
+            let line = line.trim();
+            let (command, arguments) = match line.split_once(' ') {
+                Some((command, arguments)) => (command, arguments.trim()),
+                None => (line, ""),
+            };
+
+            match command {
+                // Setup
+                "protover" => {
+                    let name = format!("{:?} ({})", engine.kind(), env!("CARGO_PKG_NAME"));
+                    self.send(format_args!(
+                        "feature myname=\"{}\" ping=1 setboard=1 usermove=1 playother=1 \
+                         sigint=0 sigterm=0 reuse=1 analyze=0 colors=0 \
+                         variants=\"normal,fischerandom\" done=1",
+                        name,
+                    ));
+                }
+
+                "new" => {
+                    log!(self.log_file, "Starting new game");
+                    self.reset();
+                    self.config.chess960 = false;
+                    self.backend.set_depth_limit(None);
+                    state = Xboard {
+                        moves_per_control: state.moves_per_control,
+                        base_time: state.base_time,
+                        increment: state.increment,
+                        move_time: state.move_time,
+                        ..Xboard::new()
+                    };
+
+                    // The engine may have been switched since the last game
+                    engine = self.build_engine();
+                }
+
+                "variant" => match arguments {
+                    "normal" => self.config.chess960 = false,
+                    "fischerandom" => self.config.chess960 = true,
+                    _ => self.send(format_args!("Error (unsupported variant): {}", arguments)),
+                },
+
+                "setboard" => {
+                    let fen = UciFen(arguments.into());
+                    if let Err(error) = self.set_position(false, Some(fen), Vec::new()) {
+                        log!(self.log_file, "Unable to set board: {}", error);
+                        self.send(format_args!("tellusererror Illegal position: {}", error));
+                    }
+                }
+
+                // Playing
+                "force" => state.force = true,
+
+                "go" => {
+                    state.force = false;
+                    state.engine_color = self.board.side_to_move();
+                    self.xboard_move(&*engine, &mut state);
+                }
+
+                "playother" => {
+                    state.force = false;
+                    state.engine_color = !self.board.side_to_move();
+                }
+
+                "usermove" => self.xboard_user_move(&*engine, &mut state, arguments),
+
+                "result" => {
+                    log!(self.log_file, "Game over: {}", arguments);
+                    state.force = true;
+                }
+
+                // Time controls
+                "level" => match parse_level(arguments) {
+                    Some((moves_per_control, base_time, increment)) => {
+                        state.moves_per_control = moves_per_control;
+                        state.base_time = base_time;
+                        state.increment = increment;
+                        state.move_time = None;
+                    }
+                    None => self.send(format_args!("Error (invalid time control): {}", line)),
+                },
+
+                "st" => match arguments.parse::<f32>() {
+                    Ok(seconds) if seconds.is_finite() && seconds > 0.0 => {
+                        state.move_time = Some(Duration::from_secs_f32(seconds));
+                    }
+                    _ => self.send(format_args!("Error (invalid time): {}", line)),
+                },
+
+                "sd" => match arguments.parse::<u8>() {
+                    Ok(depth) => self.backend.set_depth_limit(Some(depth)),
+                    Err(_) => self.send(format_args!("Error (invalid depth): {}", line)),
+                },
+
+                "time" => match arguments.parse::<u64>() {
+                    Ok(centiseconds) => {
+                        state.remaining = Some(Duration::from_millis(centiseconds * 10));
+                    }
+                    Err(_) => self.send(format_args!("Error (invalid time): {}", line)),
+                },
+
+                // Status
+                "ping" => self.send(format_args!("pong {}", arguments)),
+
+                "quit" => {
+                    log!(self.log_file, "Received quit request, breaking main loop");
+                    break;
+                }
+
+                // Commands we accept but have nothing to do for
+                "" | "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "post"
+                | "nopost" | "computer" | "name" | "rating" | "ics" | "otim" | "draw" | "?" => (),
+
+                // Old GUIs send moves without "usermove", even after we ask for it
+                _ if is_move(command) => {
+                    self.xboard_user_move(&*engine, &mut state, command);
+                }
+
+                _ => self.send(format_args!("Error (unknown command): {}", command)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Plays the opponent's move, then replies if it's our turn.
+    fn xboard_user_move(&mut self, engine: &dyn Engine, state: &mut Xboard, text: &str) {
+        let chess_move = match self.xboard_read_move(text) {
+            Some(chess_move) if self.castling.is_legal(&self.board, chess_move) => chess_move,
+            _ => {
+                self.send(format_args!("Illegal move: {}", text));
+                return;
+            }
+        };
+
+        self.play(chess_move);
+        if self.xboard_send_result() {
+            state.force = true;
+            return;
+        }
+
+        if !state.force && self.board.side_to_move() == state.engine_color {
+            self.xboard_move(engine, state);
+        }
+    }
+
+    /// Thinks of a move, plays it, and sends it to the GUI.
+    fn xboard_move(&mut self, engine: &dyn Engine, state: &mut Xboard) {
+        let budget = state.time_control().budget();
+        self.backend.set_time_budget(budget);
+
+        match self.choose_move(engine) {
+            Some((chess_move, _)) => {
+                let text = self.xboard_write_move(chess_move);
+                self.play(chess_move);
+                state.moves_played += 1;
+                self.send(format_args!("move {}", text));
+
+                if self.xboard_send_result() {
+                    state.force = true;
+                }
+            }
+            None => {
+                // Shouldn't happen, since we report the result as soon as the game ends
+                self.xboard_send_result();
+                state.force = true;
+            }
+        }
+    }
+
+    /// Reads a move in coordinate notation, or castling as `O-O` or `O-O-O`.
+    fn xboard_read_move(&self, text: &str) -> Option<ChessMove> {
+        match castle_side(text) {
+            Some(side) => self.castling.castle_move(&self.board, side),
+            None => ChessMove::from_str(text)
+                .ok()
+                .map(|chess_move| self.read_move(&self.board, chess_move)),
+        }
+    }
+
+    /// Writes a move from the current position, using `O-O` or `O-O-O` to castle in Chess960.
+    fn xboard_write_move(&self, chess_move: ChessMove) -> String {
+        let side = chess960::castle_direction(&self.board, chess_move);
+
+        match side {
+            Some(Side::King) if self.config.chess960 => "O-O".into(),
+            Some(Side::Queen) if self.config.chess960 => "O-O-O".into(),
+            _ => chess_move.to_string(),
+        }
+    }
+
+    /// Tells the GUI the result if the game has ended, returning whether it has.
+    fn xboard_send_result(&mut self) -> bool {
+        let result = match self.board.status() {
+            BoardStatus::Checkmate => match self.board.side_to_move() {
+                Color::White => "0-1 {Black mates}",
+                Color::Black => "1-0 {White mates}",
+            },
+            BoardStatus::Stalemate => "1/2-1/2 {Stalemate}",
+            BoardStatus::Ongoing if self.can_claim_draw() => {
+                "1/2-1/2 {Draw by repetition or fifty-move rule}"
+            }
+            BoardStatus::Ongoing => return false,
+        };
+
+        self.send(result);
+        true
+    }
+}
+
+/// Applies the commands which affect a running search, called from the input thread.
+pub fn apply_control(line: &str, control: &SearchControl) {
+    let command = line.split_whitespace().next().unwrap_or("");
+
+    match command {
+        // Commands which can start a search
+        "go" | "usermove" => control.start(false),
+        _ if is_move(command) => control.start(false),
+
+        // Commands which end the current search, either to move now or abandon it
+        "?" | "force" | "new" | "result" | "quit" => control.stop(),
+
+        _ => (),
+    }
+}
+
+/// Whether this command is a bare move, which older GUIs send without `usermove`.
+fn is_move(command: &str) -> bool {
+    castle_side(command).is_some() || ChessMove::from_str(command).is_ok()
+}
+
+/// Which side a castling move in xboard's notation is towards, if it is one.
+fn castle_side(text: &str) -> Option<Side> {
+    match text {
+        "O-O" | "0-0" => Some(Side::King),
+        "O-O-O" | "0-0-0" => Some(Side::Queen),
+        _ => None,
+    }
+}
+
+/// Parses the arguments of `level`: moves per time control, base time, and increment.
+///
+/// The base time is in minutes, optionally with seconds (such as `0:30`),
+/// and the increment is in seconds.
+fn parse_level(arguments: &str) -> Option<(u32, Duration, Duration)> {
+    let mut arguments = arguments.split_whitespace();
+    let moves_per_control = arguments.next()?.parse().ok()?;
+
+    let base_time = arguments.next()?;
+    let base_time = match base_time.split_once(':') {
+        Some((minutes, seconds)) => {
+            minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?
+        }
+        None => base_time.parse::<u64>().ok()? * 60,
+    };
+
+    let increment = arguments.next()?.parse::<f32>().ok()?;
+    if !increment.is_finite() || increment < 0.0 {
+        return None;
+    }
+
+    Some((
+        moves_per_control,
+        Duration::from_secs(base_time),
+        Duration::from_secs_f32(increment),
+    ))
+}
+
+#[test]
+fn levels() {
+    assert_eq!(
+        parse_level("40 5 0"),
+        Some((40, Duration::from_secs(300), Duration::from_secs(0))),
+    );
+    assert_eq!(
+        parse_level("0 2:30 1.5"),
+        Some((0, Duration::from_secs(150), Duration::from_millis(1500))),
+    );
+    assert_eq!(parse_level("40 5"), None);
+    assert_eq!(parse_level("x 5 0"), None);
+}
+
+#[test]
+fn session() {
+    use super::Session;
+    use crate::backend::mock::MockScript;
+    use crate::config::Configuration;
+    use crate::engine::EngineKind;
+    use chess::Board;
+
+    /// Reads the engine's reply and plays it on our copy of the board.
+    fn receive_move(session: &Session, board: &mut Board) {
+        let line = session.receive();
+        let text = line.strip_prefix("move ").expect("Expected a move");
+        let chess_move = ChessMove::from_str(text).expect("Invalid move");
+        assert!(board.legal(chess_move), "Illegal move {}", text);
+        *board = board.make_move_new(chess_move);
+    }
+
+    fn play(board: &mut Board, text: &str) {
+        *board = board.make_move_new(ChessMove::from_str(text).expect("Invalid move"));
+    }
+
+    let mut config = Configuration::mock(EngineKind::Random, MockScript::new());
+    config.protocol = None;
+    let session = Session::start(config);
+
+    // Setup
+    session.send("xboard");
+    session.send("protover 2");
+    let features = session.receive();
+    assert!(features.starts_with("feature myname="), "{}", features);
+    for feature in &["ping=1", "setboard=1", "usermove=1", "done=1"] {
+        assert!(features.contains(feature), "Missing {}", feature);
+    }
+
+    // We play black by default, so reply to each move
+    session.send("new");
+    let mut board = Board::default();
+    session.send("usermove e2e4");
+    play(&mut board, "e2e4");
+    receive_move(&session, &mut board);
+
+    // Old GUIs leave out "usermove"
+    session.send("d2d4");
+    play(&mut board, "d2d4");
+    receive_move(&session, &mut board);
+
+    // In force mode, moves are only played
+    session.send("force");
+    session.send("usermove g1f3");
+    play(&mut board, "g1f3");
+    session.send("ping 1");
+    assert_eq!(session.receive(), "pong 1");
+
+    // Until we're told to move for the side to play
+    session.send("go");
+    receive_move(&session, &mut board);
+
+    // Illegal moves are refused
+    session.send("usermove e1e8");
+    assert_eq!(session.receive(), "Illegal move: e1e8");
+
+    // The result is reported once the game ends
+    session.send("new");
+    session.send("force");
+    session.send("setboard rnbqkbnr/ppppp2p/5p2/6p1/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3");
+    session.send("usermove d1h5");
+    assert_eq!(session.receive(), "1-0 {White mates}");
+
+    // After the GUI's result, we stay quiet
+    session.send("result 1-0 {White mates}");
+    session.send("ping 2");
+    assert_eq!(session.receive(), "pong 2");
+
+    session.quit();
+}
@@ -0,0 +1,129 @@
+/*
+ * history.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Tracking of previous positions, for draws by repetition and the fifty-move rule.
+//!
+//! Only positions since the last capture or pawn move are kept, since
+//! none of the positions before it can ever occur again.
+
+use crate::chess960;
+use chess::{Board, ChessMove, Piece};
+
+/// How many halfmoves without a capture or pawn move allow claiming a draw.
+const FIFTY_MOVES: u32 = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History {
+    /// Hashes of each position since the last irreversible move, ending with the current one.
+    hashes: Vec<u64>,
+
+    /// How many halfmoves since the last capture or pawn move.
+    halfmove_clock: u32,
+}
+
+impl History {
+    /// Starts a history from this position, which has no known predecessors.
+    pub fn new(board: &Board, halfmove_clock: u32) -> Self {
+        History {
+            hashes: vec![board.get_hash()],
+            halfmove_clock,
+        }
+    }
+
+    /// Records a move, made from `board`.
+    pub fn push(&mut self, board: &Board, chess_move: ChessMove) {
+        // Castling in Chess960 looks like the king capturing its own rook
+        let is_capture = board.color_on(chess_move.get_dest()) == Some(!board.side_to_move());
+        let is_pawn_move = board.piece_on(chess_move.get_source()) == Some(Piece::Pawn);
+
+        if is_capture || is_pawn_move {
+            self.hashes.clear();
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        self.hashes
+            .push(chess960::make_move(board, chess_move).get_hash());
+    }
+
+    /// Returns the history as it would be after making this move from `board`.
+    pub fn after(&self, board: &Board, chess_move: ChessMove) -> Self {
+        let mut history = self.clone();
+        history.push(board, chess_move);
+        history
+    }
+
+    #[inline]
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    /// How many times the current position has occurred, including now.
+    pub fn occurrences(&self) -> usize {
+        let current = self.hashes.last().expect("History has no positions");
+        self.hashes.iter().filter(|&hash| hash == current).count()
+    }
+
+    /// Whether the current position has occurred before.
+    #[inline]
+    pub fn is_repetition(&self) -> bool {
+        self.occurrences() >= 2
+    }
+
+    /// Whether either player may claim a draw, by threefold repetition or the fifty-move rule.
+    #[inline]
+    pub fn can_claim_draw(&self) -> bool {
+        self.occurrences() >= 3 || self.halfmove_clock >= FIFTY_MOVES
+    }
+}
+
+#[test]
+fn repetition() {
+    use std::str::FromStr;
+
+    let mut board = Board::default();
+    let mut history = History::new(&board, 0);
+    let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+    for (index, chess_move) in shuffle.iter().chain(&shuffle).enumerate() {
+        let chess_move = ChessMove::from_str(chess_move).expect("Invalid move");
+        history.push(&board, chess_move);
+        board = board.make_move_new(chess_move);
+
+        match index {
+            0..=2 => assert!(!history.is_repetition()),
+            3..=6 => assert!(history.is_repetition() && !history.can_claim_draw()),
+            _ => assert!(history.can_claim_draw()),
+        }
+    }
+
+    assert_eq!(history.halfmove_clock(), 8);
+
+    // A pawn move can never be repeated
+    let chess_move = ChessMove::from_str("e2e4").expect("Invalid move");
+    history.push(&board, chess_move);
+    assert_eq!(history.halfmove_clock(), 0);
+    assert!(!history.is_repetition());
+}
+
+#[test]
+fn fifty_moves() {
+    use std::str::FromStr;
+
+    let board = Board::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").expect("Invalid FEN");
+    let history = History::new(&board, 99);
+    assert!(!history.can_claim_draw());
+
+    let chess_move = ChessMove::from_str("a1a2").expect("Invalid move");
+    assert!(history.after(&board, chess_move).can_claim_draw());
+}
@@ -13,19 +13,46 @@
 #[macro_use]
 mod macros;
 
+mod antichess;
+mod backend;
+mod cache;
+mod chess960;
+mod clock;
 mod config;
 mod engine;
+mod error;
 mod game;
+mod history;
+mod options;
+mod san;
 mod score;
-mod stockfish;
+mod squares;
 
-use self::config::Configuration;
+use self::config::{Configuration, Invocation};
+use self::engine::EngineKind;
+use self::error::{Error, Result};
 use self::game::Game;
+use std::process;
 
 fn main() {
-    let config = Configuration::load();
-    let engine = config.engine_kind.build(&config);
-    let mut game = Game::new(config);
+    if let Err(error) = run() {
+        eprintln!("{}", error);
 
-    game.main_loop(&*engine);
+        if let Error::UnknownEngine(_) = error {
+            EngineKind::print_variants();
+        }
+
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    match Configuration::load()? {
+        Invocation::Play(config) => {
+            let mut game = Game::new(*config)?;
+
+            game.main_loop()
+        }
+        Invocation::ListEngines(format) => engine::print_engines(format),
+    }
 }
@@ -0,0 +1,410 @@
+/*
+ * options.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! UCI options which let the GUI change our settings.
+//!
+//! These mirror the command-line arguments in `Configuration`,
+//! so a single engine entry in the GUI can cover every mode.
+//! The exceptions are `Ponder`, `UCI_Chess960` and `UCI_Variant`, which only the GUI can set.
+
+use crate::backend::EvaluationMode;
+use crate::config::Configuration;
+use crate::engine::{EngineKind, Notation, Weights};
+use crate::game::Variant;
+use crate::squares::{ColorMode, Metric, SquareColor};
+use std::convert::TryFrom;
+use strum::IntoEnumIterator;
+use vampirc_uci::UciOptionConfig;
+
+const ENGINE: &str = "Engine";
+const NODES: &str = "Nodes";
+const EVALUATION_MODE: &str = "EvaluationMode";
+const SCOVILLE_PERCENT: &str = "ScovillePercent";
+const ALPHABETICAL_NOTATION: &str = "AlphabeticalNotation";
+const ALPHABETICAL_ORDER: &str = "AlphabeticalOrder";
+const HUDDLE_METRIC: &str = "HuddleMetric";
+const SWARM_METRIC: &str = "SwarmMetric";
+const GENEROUS_WEIGHTS: &str = "GenerousWeights";
+const GENEROUS_MODE: &str = "GenerousMode";
+const SAME_COLOR_MODE: &str = "SameColorMode";
+const OPPOSITE_COLOR_MODE: &str = "OppositeColorMode";
+const PONDER: &str = "Ponder";
+const CHESS960: &str = "UCI_Chess960";
+const VARIANT: &str = "UCI_Variant";
+
+/// The largest node limit we advertise, since some GUIs struggle with huge spin values.
+const MAX_NODES: i64 = 1_000_000_000;
+
+/// Lists the options to advertise in reply to `uci`, with the current values as defaults.
+pub fn uci_options(config: &Configuration) -> Vec<UciOptionConfig> {
+    vec![
+        UciOptionConfig::Combo {
+            name: ENGINE.into(),
+            default: Some(format!("{:?}", config.engine_kind)),
+            var: EngineKind::iter()
+                .map(|kind| format!("{:?}", kind))
+                .collect(),
+        },
+        UciOptionConfig::Spin {
+            name: NODES.into(),
+            default: Some(config.backend_nodes.map_or(0, |nodes| nodes as i64)),
+            min: Some(0),
+            max: Some(MAX_NODES),
+        },
+        UciOptionConfig::Combo {
+            name: EVALUATION_MODE.into(),
+            default: Some(evaluation_mode_name(config.evaluation_mode).into()),
+            var: vec!["multipv".into(), "per-move".into()],
+        },
+        UciOptionConfig::Spin {
+            name: SCOVILLE_PERCENT.into(),
+            default: Some(config.scoville_percent.round() as i64),
+            min: Some(1),
+            max: Some(100),
+        },
+        UciOptionConfig::Combo {
+            name: ALPHABETICAL_NOTATION.into(),
+            default: Some(config.alphabetical_notation.name().into()),
+            var: vec![Notation::San.name().into(), Notation::Uci.name().into()],
+        },
+        UciOptionConfig::Combo {
+            name: ALPHABETICAL_ORDER.into(),
+            default: Some(
+                if config.alphabetical_last {
+                    "last"
+                } else {
+                    "first"
+                }
+                .into(),
+            ),
+            var: vec!["first".into(), "last".into()],
+        },
+        UciOptionConfig::Combo {
+            name: HUDDLE_METRIC.into(),
+            default: Some(config.huddle_metric.name().into()),
+            var: metric_names(),
+        },
+        UciOptionConfig::Combo {
+            name: SWARM_METRIC.into(),
+            default: Some(config.swarm_metric.name().into()),
+            var: metric_names(),
+        },
+        UciOptionConfig::String {
+            name: GENEROUS_WEIGHTS.into(),
+            default: Some(config.generous_weights.to_string()),
+        },
+        UciOptionConfig::Combo {
+            name: GENEROUS_MODE.into(),
+            default: Some(
+                if config.generous_queen_first {
+                    "queen-first"
+                } else {
+                    "material"
+                }
+                .into(),
+            ),
+            var: vec!["material".into(), "queen-first".into()],
+        },
+        UciOptionConfig::Combo {
+            name: SAME_COLOR_MODE.into(),
+            default: Some(config.same_color_mode.name().into()),
+            var: color_mode_names(),
+        },
+        UciOptionConfig::Combo {
+            name: OPPOSITE_COLOR_MODE.into(),
+            default: Some(config.opposite_color_mode.name().into()),
+            var: color_mode_names(),
+        },
+        UciOptionConfig::Check {
+            name: PONDER.into(),
+            default: Some(config.ponder),
+        },
+        UciOptionConfig::Check {
+            name: CHESS960.into(),
+            default: Some(config.chess960),
+        },
+        UciOptionConfig::Combo {
+            name: VARIANT.into(),
+            default: Some(config.variant.uci_name().into()),
+            var: vec![
+                Variant::Standard.uci_name().into(),
+                Variant::Antichess.uci_name().into(),
+            ],
+        },
+    ]
+}
+
+/// Applies a `setoption` command to the configuration.
+///
+/// Returns an error message if the option is unknown or the value is invalid.
+pub fn set_option(
+    config: &mut Configuration,
+    name: &str,
+    value: Option<&str>,
+) -> Result<(), String> {
+    let value = value
+        .map(str::trim)
+        .ok_or_else(|| format!("Option {} requires a value", name))?;
+
+    if name.eq_ignore_ascii_case(ENGINE) {
+        let engine_kind = EngineKind::try_from(value)
+            .map_err(|value| format!("Unknown game engine: {}", value))?;
+
+        config.set_engine(engine_kind);
+    } else if name.eq_ignore_ascii_case(NODES) {
+        let nodes = value
+            .parse::<u64>()
+            .map_err(|error| format!("Invalid node count: {} {}", value, error))?;
+
+        config.set_backend_nodes(if nodes == 0 { None } else { Some(nodes) });
+    } else if name.eq_ignore_ascii_case(EVALUATION_MODE) {
+        config.set_evaluation_mode(match value {
+            "multipv" => EvaluationMode::MultiPv,
+            "per-move" => EvaluationMode::PerMove,
+            _ => return Err(format!("Unknown evaluation mode: {}", value)),
+        });
+    } else if name.eq_ignore_ascii_case(SCOVILLE_PERCENT) {
+        let percent = value
+            .parse::<f32>()
+            .map_err(|error| format!("Invalid Scoville engine percent: {} {}", value, error))?;
+
+        if !(1.0..=100.0).contains(&percent) {
+            return Err(format!("Scoville engine percent out of range: {}", value));
+        }
+
+        config.scoville_percent = percent;
+    } else if name.eq_ignore_ascii_case(ALPHABETICAL_NOTATION) {
+        config.alphabetical_notation = match value {
+            "san" => Notation::San,
+            "uci" => Notation::Uci,
+            _ => return Err(format!("Unknown notation: {}", value)),
+        };
+    } else if name.eq_ignore_ascii_case(ALPHABETICAL_ORDER) {
+        config.alphabetical_last = match value {
+            "first" => false,
+            "last" => true,
+            _ => return Err(format!("Unknown alphabetical order: {}", value)),
+        };
+    } else if name.eq_ignore_ascii_case(HUDDLE_METRIC) {
+        config.huddle_metric =
+            Metric::from_name(value).ok_or_else(|| format!("Unknown metric: {}", value))?;
+    } else if name.eq_ignore_ascii_case(SWARM_METRIC) {
+        config.swarm_metric =
+            Metric::from_name(value).ok_or_else(|| format!("Unknown metric: {}", value))?;
+    } else if name.eq_ignore_ascii_case(GENEROUS_WEIGHTS) {
+        config.generous_weights = value
+            .parse::<Weights>()
+            .map_err(|error| format!("Invalid Generous engine weights: {} {}", value, error))?;
+    } else if name.eq_ignore_ascii_case(GENEROUS_MODE) {
+        config.generous_queen_first = match value {
+            "material" => false,
+            "queen-first" => true,
+            _ => return Err(format!("Unknown Generous engine mode: {}", value)),
+        };
+    } else if name.eq_ignore_ascii_case(SAME_COLOR_MODE) {
+        config.same_color_mode = ColorMode::from_name(value)
+            .ok_or_else(|| format!("Unknown square color mode: {}", value))?;
+    } else if name.eq_ignore_ascii_case(OPPOSITE_COLOR_MODE) {
+        config.opposite_color_mode = ColorMode::from_name(value)
+            .ok_or_else(|| format!("Unknown square color mode: {}", value))?;
+    } else if name.eq_ignore_ascii_case(PONDER) {
+        config.ponder = value
+            .parse()
+            .map_err(|error| format!("Invalid ponder setting: {} {}", value, error))?;
+    } else if name.eq_ignore_ascii_case(CHESS960) {
+        config.chess960 = value
+            .parse()
+            .map_err(|error| format!("Invalid Chess960 setting: {} {}", value, error))?;
+    } else if name.eq_ignore_ascii_case(VARIANT) {
+        config.variant = match value {
+            "chess" => Variant::Standard,
+            "antichess" => Variant::Antichess,
+            _ => return Err(format!("Unsupported variant: {}", value)),
+        };
+    } else {
+        return Err(format!("Unknown option: {}", name));
+    }
+
+    Ok(())
+}
+
+fn metric_names() -> Vec<String> {
+    vec![
+        Metric::Chebyshev.name().into(),
+        Metric::Manhattan.name().into(),
+    ]
+}
+
+fn color_mode_names() -> Vec<String> {
+    vec![
+        ColorMode::King.name().into(),
+        ColorMode::Fixed(SquareColor::Light).name().into(),
+        ColorMode::Fixed(SquareColor::Dark).name().into(),
+    ]
+}
+
+fn evaluation_mode_name(mode: EvaluationMode) -> &'static str {
+    match mode {
+        EvaluationMode::MultiPv => "multipv",
+        EvaluationMode::PerMove => "per-move",
+    }
+}
+
+#[test]
+fn set_options() {
+    use crate::backend::mock::MockScript;
+
+    type Check = fn(&Configuration) -> bool;
+
+    // Each option with a valid value, how to tell it was applied, and an invalid value
+    let cases: &[(&str, &str, Check, &str)] = &[
+        (
+            ENGINE,
+            "greedy",
+            |c| c.engine_kind == EngineKind::Greedy,
+            "fishy",
+        ),
+        (NODES, "5000", |c| c.backend_nodes == Some(5000), "lots"),
+        (
+            EVALUATION_MODE,
+            "per-move",
+            |c| c.evaluation_mode == EvaluationMode::PerMove,
+            "fast",
+        ),
+        (SCOVILLE_PERCENT, "25", |c| c.scoville_percent == 25.0, "0"),
+        (
+            ALPHABETICAL_NOTATION,
+            "uci",
+            |c| c.alphabetical_notation == Notation::Uci,
+            "lan",
+        ),
+        (
+            ALPHABETICAL_ORDER,
+            "last",
+            |c| c.alphabetical_last,
+            "middle",
+        ),
+        (
+            HUDDLE_METRIC,
+            "manhattan",
+            |c| c.huddle_metric == Metric::Manhattan,
+            "euclidean",
+        ),
+        (
+            SWARM_METRIC,
+            "manhattan",
+            |c| c.swarm_metric == Metric::Manhattan,
+            "euclidean",
+        ),
+        (
+            GENEROUS_WEIGHTS,
+            "1,1,1,1,1",
+            |c| c.generous_weights.queen == 1,
+            "1,2",
+        ),
+        (
+            GENEROUS_MODE,
+            "queen-first",
+            |c| c.generous_queen_first,
+            "all",
+        ),
+        (
+            SAME_COLOR_MODE,
+            "dark",
+            |c| c.same_color_mode == ColorMode::Fixed(SquareColor::Dark),
+            "grey",
+        ),
+        (
+            OPPOSITE_COLOR_MODE,
+            "light",
+            |c| c.opposite_color_mode == ColorMode::Fixed(SquareColor::Light),
+            "grey",
+        ),
+        (PONDER, "true", |c| c.ponder, "maybe"),
+        (CHESS960, "true", |c| c.chess960, "maybe"),
+        (
+            VARIANT,
+            "antichess",
+            |c| c.variant == Variant::Antichess,
+            "crazyhouse",
+        ),
+    ];
+
+    for &(name, valid, applied, invalid) in cases {
+        let mut config = Configuration::mock(EngineKind::Random, MockScript::new());
+        assert!(!applied(&config), "{} already set", name);
+
+        set_option(&mut config, name, Some(valid)).expect("Unable to set option");
+        assert!(applied(&config), "{} not applied", name);
+
+        // Failures leave the previous value in place
+        assert!(set_option(&mut config, name, Some(invalid)).is_err());
+        assert!(set_option(&mut config, name, None).is_err());
+        assert!(applied(&config), "{} changed by invalid value", name);
+    }
+
+    let mut config = Configuration::mock(EngineKind::Random, MockScript::new());
+    assert!(set_option(&mut config, "Hash", Some("256")).is_err());
+}
+
+#[test]
+fn engine_sections() {
+    use crate::backend::mock::MockScript;
+    use crate::config::EngineOverrides;
+
+    let mut config = Configuration::mock(EngineKind::Random, MockScript::new());
+    config.engine_overrides.insert(
+        EngineKind::Worstfish,
+        EngineOverrides {
+            backend_nodes: Some(500),
+            evaluation_mode: Some(EvaluationMode::PerMove),
+        },
+    );
+
+    // Switching engines applies their own settings, or the global ones
+    set_option(&mut config, ENGINE, Some("worstfish")).expect("Unable to set option");
+    assert_eq!(config.backend_nodes, Some(500));
+    assert_eq!(config.evaluation_mode, EvaluationMode::PerMove);
+
+    set_option(&mut config, ENGINE, Some("random")).expect("Unable to set option");
+    assert_eq!(config.backend_nodes, None);
+    assert_eq!(config.evaluation_mode, EvaluationMode::MultiPv);
+
+    // Settings from the GUI apply to every engine
+    set_option(&mut config, NODES, Some("100")).expect("Unable to set option");
+    set_option(&mut config, ENGINE, Some("worstfish")).expect("Unable to set option");
+    assert_eq!(config.backend_nodes, Some(100));
+    assert_eq!(config.evaluation_mode, EvaluationMode::PerMove);
+}
+
+#[test]
+fn combo_values() {
+    use crate::backend::mock::MockScript;
+
+    // Every value we advertise has to be accepted when the GUI sends it back
+    let config = Configuration::mock(EngineKind::Random, MockScript::new());
+    for option in uci_options(&config) {
+        if let UciOptionConfig::Combo { name, var, .. } = option {
+            for value in var {
+                let mut config = Configuration::mock(EngineKind::Random, MockScript::new());
+                set_option(&mut config, &name, Some(&value))
+                    .unwrap_or_else(|error| panic!("{} = {} rejected: {}", name, value, error));
+            }
+        }
+    }
+
+    let mut config = Configuration::mock(EngineKind::Random, MockScript::new());
+    for kind in EngineKind::iter() {
+        set_option(&mut config, ENGINE, Some(&format!("{:?}", kind))).expect("Engine rejected");
+        assert_eq!(config.engine_kind, kind);
+    }
+}
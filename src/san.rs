@@ -0,0 +1,169 @@
+/*
+ * san.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Formatting moves in Standard Algebraic Notation, as used in PGN.
+//!
+//! Castling may be given either as the king moving two files, or as the
+//! king capturing its own rook, as in Chess960.
+
+use crate::chess960::{self, Side};
+use chess::{Board, BoardStatus, ChessMove, MoveGen, Piece, Square, EMPTY};
+
+/// Formats a legal move on this board, such as `Nbd2`, `exd6`, `e8=Q+` or `O-O-O`.
+pub fn to_san(board: &Board, chess_move: ChessMove) -> String {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+    let piece = board
+        .piece_on(source)
+        .expect("No piece on source square of move");
+
+    let mut san = String::new();
+
+    if let Some(side) = chess960::castle_direction(board, chess_move) {
+        san.push_str(match side {
+            Side::King => "O-O",
+            Side::Queen => "O-O-O",
+        });
+    } else {
+        // Capturing en passant is the only capture onto an empty square
+        let capture = board.piece_on(dest).is_some()
+            || (piece == Piece::Pawn && file_distance(source, dest) != 0);
+
+        if piece == Piece::Pawn {
+            if capture {
+                san.push(file_char(source));
+            }
+        } else {
+            san.push(piece_char(piece));
+            san.push_str(&disambiguation(board, chess_move, piece));
+        }
+
+        if capture {
+            san.push('x');
+        }
+
+        san.push_str(&dest.to_string());
+
+        if let Some(promotion) = chess_move.get_promotion() {
+            san.push('=');
+            san.push(piece_char(promotion));
+        }
+    }
+
+    let new_board = chess960::make_move(board, chess_move);
+    if new_board.checkers() != &EMPTY {
+        san.push(match new_board.status() {
+            BoardStatus::Checkmate => '#',
+            _ => '+',
+        });
+    }
+
+    san
+}
+
+/// The file, rank or square needed to tell this move apart from
+/// moves of other pieces of the same kind to the same square.
+fn disambiguation(board: &Board, chess_move: ChessMove, piece: Piece) -> String {
+    let source = chess_move.get_source();
+    let others = MoveGen::new_legal(board)
+        .filter(|other| {
+            other.get_dest() == chess_move.get_dest()
+                && other.get_source() != source
+                && board.piece_on(other.get_source()) == Some(piece)
+        })
+        .map(|other| other.get_source())
+        .collect::<Vec<_>>();
+
+    if others.is_empty() {
+        String::new()
+    } else if others
+        .iter()
+        .all(|other| other.get_file() != source.get_file())
+    {
+        file_char(source).to_string()
+    } else if others
+        .iter()
+        .all(|other| other.get_rank() != source.get_rank())
+    {
+        rank_char(source).to_string()
+    } else {
+        source.to_string()
+    }
+}
+
+fn file_distance(source: Square, dest: Square) -> usize {
+    source
+        .get_file()
+        .to_index()
+        .abs_diff(dest.get_file().to_index())
+}
+
+fn file_char(square: Square) -> char {
+    (b'a' + square.get_file().to_index() as u8) as char
+}
+
+fn rank_char(square: Square) -> char {
+    (b'1' + square.get_rank().to_index() as u8) as char
+}
+
+fn piece_char(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
+
+#[test]
+fn formatting() {
+    use std::str::FromStr;
+
+    let check = |fen: &str, chess_move: &str, expected: &str| {
+        let board = Board::from_str(fen).expect("Invalid FEN");
+        let chess_move = ChessMove::from_str(chess_move).expect("Invalid move");
+        assert_eq!(
+            to_san(&board, chess_move),
+            expected,
+            "{} in {}",
+            chess_move,
+            fen
+        );
+    };
+
+    // Pawn pushes, piece moves and castling
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    check(start, "e2e4", "e4");
+    check(start, "g1f3", "Nf3");
+    check("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1", "O-O");
+    check("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8", "O-O-O");
+    check("4k3/8/8/8/8/8/8/1R3K1R w - - 0 1", "f1h1", "O-O");
+    check("4k3/8/8/8/8/8/8/1R3K1R w - - 0 1", "f1b1", "O-O-O");
+
+    // Captures, including en passant
+    check("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6");
+    check("4k3/8/3p4/8/4N3/8/8/4K3 w - - 0 1", "e4d6", "Nxd6+");
+    check("4k3/8/8/8/8/8/3p4/3QK3 w - - 0 1", "e1d2", "Kxd2");
+
+    // Disambiguation by file, rank, and both
+    check("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1d2", "Nbd2");
+    check("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3", "R1a3");
+    check("8/8/k7/8/7Q/8/8/K6Q w - - 0 1", "h4e1", "Q4e1");
+    check("8/8/k7/8/4Q2Q/8/8/K6Q w - - 0 1", "h4e1", "Qh4e1");
+
+    // Promotions, checks and mates
+    check("8/4P3/8/8/8/8/8/k3K3 w - - 0 1", "e7e8q", "e8=Q");
+    check("k7/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8r", "e8=R+");
+    check("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8", "Ra8#");
+}
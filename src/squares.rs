@@ -0,0 +1,184 @@
+/*
+ * squares.rs
+ *
+ * mallard-chess - Chess engine wrapper for fun
+ * Copyright (C) 2022 Ammon Smith
+ *
+ * mallard-chess is available free of charge under the terms of the MIT
+ * License. You are free to redistribute and/or modify it under those
+ * terms. It is distributed in the hopes that it will be useful, but
+ * WITHOUT ANY WARRANTY. See the LICENSE file for more details.
+ */
+
+//! Geometry of the squares on the board, for engines which care where their pieces stand.
+
+use chess::{Board, Color, Square};
+use std::ops::Not;
+
+/// How to measure the distance between two squares.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Metric {
+    /// How many moves a king would need, so diagonal steps count as one.
+    Chebyshev,
+
+    /// How many files plus how many ranks apart the squares are.
+    Manhattan,
+}
+
+impl Metric {
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Chebyshev => "chebyshev",
+            Metric::Manhattan => "manhattan",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "chebyshev" => Some(Metric::Chebyshev),
+            "manhattan" => Some(Metric::Manhattan),
+            _ => None,
+        }
+    }
+
+    pub fn distance(self, square1: Square, square2: Square) -> u32 {
+        let files = square1
+            .get_file()
+            .to_index()
+            .abs_diff(square2.get_file().to_index()) as u32;
+        let ranks = square1
+            .get_rank()
+            .to_index()
+            .abs_diff(square2.get_rank().to_index()) as u32;
+
+        match self {
+            Metric::Chebyshev => files.max(ranks),
+            Metric::Manhattan => files + ranks,
+        }
+    }
+}
+
+/// Adds up the distance from each of this player's pieces to the target square.
+pub fn total_distance(board: &Board, color: Color, target: Square, metric: Metric) -> u32 {
+    board
+        .color_combined(color)
+        .map(|square| metric.distance(square, target))
+        .sum()
+}
+
+/// The color of a square on the board, not of the pieces standing on it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SquareColor {
+    Light,
+    Dark,
+}
+
+impl SquareColor {
+    /// The color of this square, where `a1` is dark.
+    pub fn of(square: Square) -> Self {
+        if (square.get_file().to_index() + square.get_rank().to_index()) % 2 == 0 {
+            SquareColor::Dark
+        } else {
+            SquareColor::Light
+        }
+    }
+}
+
+impl Not for SquareColor {
+    type Output = SquareColor;
+
+    fn not(self) -> SquareColor {
+        match self {
+            SquareColor::Light => SquareColor::Dark,
+            SquareColor::Dark => SquareColor::Light,
+        }
+    }
+}
+
+/// Which square color an engine aims for, either fixed or taken from the game.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorMode {
+    Fixed(SquareColor),
+
+    /// The color of the square this player's king started the game on.
+    King,
+}
+
+impl ColorMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorMode::Fixed(SquareColor::Light) => "light",
+            ColorMode::Fixed(SquareColor::Dark) => "dark",
+            ColorMode::King => "king",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(ColorMode::Fixed(SquareColor::Light)),
+            "dark" => Some(ColorMode::Fixed(SquareColor::Dark)),
+            "king" => Some(ColorMode::King),
+            _ => None,
+        }
+    }
+
+    /// The square color this player aims for, given the position the game started from.
+    pub fn square_color(self, start: &Board, color: Color) -> SquareColor {
+        match self {
+            ColorMode::Fixed(square_color) => square_color,
+            ColorMode::King => SquareColor::of(start.king_square(color)),
+        }
+    }
+}
+
+/// Counts how many of this player's pieces stand on squares of the given color.
+pub fn pieces_on(board: &Board, color: Color, square_color: SquareColor) -> u32 {
+    board
+        .color_combined(color)
+        .filter(|&square| SquareColor::of(square) == square_color)
+        .count() as u32
+}
+
+#[test]
+fn distances() {
+    use chess::{File, Rank};
+
+    let a1 = Square::make_square(Rank::First, File::A);
+    let c2 = Square::make_square(Rank::Second, File::C);
+    let h8 = Square::make_square(Rank::Eighth, File::H);
+
+    assert_eq!(Metric::Chebyshev.distance(a1, c2), 2);
+    assert_eq!(Metric::Manhattan.distance(a1, c2), 3);
+    assert_eq!(Metric::Chebyshev.distance(a1, h8), 7);
+    assert_eq!(Metric::Manhattan.distance(a1, h8), 14);
+    assert_eq!(Metric::Manhattan.distance(c2, c2), 0);
+}
+
+#[test]
+fn square_colors() {
+    use chess::{File, Rank};
+
+    let a1 = Square::make_square(Rank::First, File::A);
+    let e1 = Square::make_square(Rank::First, File::E);
+    let e8 = Square::make_square(Rank::Eighth, File::E);
+    let h1 = Square::make_square(Rank::First, File::H);
+
+    assert_eq!(SquareColor::of(a1), SquareColor::Dark);
+    assert_eq!(SquareColor::of(e1), SquareColor::Dark);
+    assert_eq!(SquareColor::of(e8), SquareColor::Light);
+    assert_eq!(SquareColor::of(h1), SquareColor::Light);
+    assert_eq!(!SquareColor::Light, SquareColor::Dark);
+
+    // Each side starts with eight pieces on either color
+    let board = Board::default();
+    assert_eq!(pieces_on(&board, Color::White, SquareColor::Light), 8);
+    assert_eq!(pieces_on(&board, Color::Black, SquareColor::Dark), 8);
+    assert_eq!(
+        ColorMode::King.square_color(&board, Color::White),
+        SquareColor::Dark,
+    );
+    assert_eq!(
+        ColorMode::King.square_color(&board, Color::Black),
+        SquareColor::Light,
+    );
+}